@@ -1,6 +1,6 @@
 use std::{path::Path, time::Duration};
 
-use notify::RecursiveMode;
+use notify::{RecursiveMode, Watcher};
 use notify_debouncer_mini::new_debouncer;
 
 /// Example for debouncer mini
@@ -46,7 +46,9 @@ fn main() {
             Ok(events) => events
                 .iter()
                 .for_each(|event| log::info!("Event {event:?}")),
-            Err(error) => log::info!("Error {error:?}"),
+            Err(errors) => errors
+                .iter()
+                .for_each(|error| log::info!("Error {error:?}")),
         }
     }
 }
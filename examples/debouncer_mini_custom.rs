@@ -1,6 +1,6 @@
 use std::{path::Path, time::Duration};
 
-use notify::{self, RecursiveMode};
+use notify::{self, RecursiveMode, Watcher};
 use notify_debouncer_mini::{new_debouncer_opt, Config};
 
 /// Debouncer with custom backend and waiting for exit
@@ -34,7 +34,7 @@ fn main() {
     for result in rx {
         match result {
             Ok(event) => println!("Event {event:?}"),
-            Err(error) => println!("Error {error:?}"),
+            Err(errors) => errors.iter().for_each(|error| println!("Error {error:?}")),
         }
     }
 }
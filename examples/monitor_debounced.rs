@@ -1,4 +1,4 @@
-use notify::{RecursiveMode, Watcher};
+use notify::RecursiveMode;
 use notify_debouncer_full::new_debouncer;
 use std::{path::Path, time::Duration};
 
@@ -26,17 +26,11 @@ fn watch<P: AsRef<Path>>(path: P) -> notify::Result<()> {
 
     // Add a path to be watched. All files and directories at that path and
     // below will be monitored for changes.
-    debouncer
-        .watcher()
-        .watch(path.as_ref(), RecursiveMode::Recursive)?;
-
-    // Initialize the file id cache for the same path. This will allow the debouncer to stitch together move events,
+    // This initializes the file id cache for the same path as well. This will allow the debouncer to stitch together move events,
     // even if the underlying watch implementation doesn't support it.
     // Without the cache and with some watch implementations,
     // you may receive `move from` and `move to` events instead of one `move both` event.
-    debouncer
-        .cache()
-        .add_root(path.as_ref(), RecursiveMode::Recursive);
+    debouncer.watch(path.as_ref(), RecursiveMode::Recursive)?;
 
     // print all events and errors
     for result in rx {
@@ -1,20 +1,104 @@
-use std::io;
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
 
 use file_id::FileId;
 
+const USAGE: &str = "\
+Usage: file-id [--recursive] [--json] <path>...
+       file-id --compare [--json] <path> <path>
+
+Options:
+  -r, --recursive  List the file IDs of all files and directories below the paths
+  -j, --json       Print one JSON object per line (requires the `cli-json` feature)
+  -c, --compare    Check if two paths refer to the same file, exits with 1 if they don't and
+                   with 2 if one of them can't be read";
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    recursive: bool,
+    json: bool,
+    compare: bool,
+    paths: Vec<PathBuf>,
+}
+
+/// Returns `None` if the help was requested
+fn parse_args(raw_args: impl IntoIterator<Item = OsString>) -> Result<Option<Args>, String> {
+    let mut args = Args::default();
+
+    for arg in raw_args {
+        match arg.to_str() {
+            Some("-r" | "--recursive") => args.recursive = true,
+            Some("-j" | "--json") => args.json = true,
+            Some("-c" | "--compare") => args.compare = true,
+            Some("-h" | "--help") => return Ok(None),
+            Some(flag) if flag.starts_with('-') => return Err(format!("unknown option {flag}")),
+            _ => args.paths.push(arg.into()),
+        }
+    }
+
+    if args.paths.is_empty() {
+        return Err("no path given".to_string());
+    }
+    if args.compare && args.recursive {
+        return Err("--compare can't be combined with --recursive".to_string());
+    }
+    if args.compare && args.paths.len() != 2 {
+        return Err("--compare takes exactly two paths".to_string());
+    }
+    if args.json && !cfg!(feature = "cli-json") {
+        return Err("JSON output requires the `cli-json` feature".to_string());
+    }
+
+    Ok(Some(args))
+}
+
 fn main() {
-    let path = std::env::args().nth(1).expect("no path given");
+    let args = match parse_args(std::env::args_os().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(error) => {
+            eprintln!("Error: {error}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    if args.compare {
+        let code = match compare(&args.paths[0], &args.paths[1], args.json) {
+            Some(true) => 0,
+            Some(false) => 1,
+            None => 2,
+        };
+        process::exit(code);
+    }
 
-    print_file_id(&path);
+    if args.paths.len() == 1 && !args.recursive && !args.json {
+        print_file_id(&args.paths[0]);
+        return;
+    }
+
+    for path in &args.paths {
+        if args.recursive {
+            walk(path, &mut |path| print_entry(path, args.json));
+        } else {
+            print_entry(path, args.json);
+        }
+    }
 }
 
 #[cfg(target_family = "unix")]
-fn print_file_id(path: &str) {
+fn print_file_id(path: &Path) {
     print_result(file_id::get_file_id(path));
 }
 
 #[cfg(target_family = "windows")]
-fn print_file_id(path: &str) {
+fn print_file_id(path: &Path) {
     print_result(file_id::get_low_res_file_id(path));
     print_result(file_id::get_high_res_file_id(path));
 }
@@ -25,3 +109,163 @@ fn print_result(result: io::Result<FileId>) {
         Err(error) => println!("Error: {error}"),
     }
 }
+
+/// Call `f` for `path` and all files and directories below it, without following symbolic links
+fn walk(path: &Path, f: &mut dyn FnMut(&Path)) {
+    f(path);
+
+    if !matches!(fs::symlink_metadata(path), Ok(metadata) if metadata.is_dir()) {
+        return;
+    }
+
+    match fs::read_dir(path) {
+        Ok(entries) => {
+            let mut paths: Vec<_> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect();
+            paths.sort();
+            for path in paths {
+                walk(&path, f);
+            }
+        }
+        Err(error) => eprintln!("Error: {}: {error}", path.display()),
+    }
+}
+
+fn print_entry(path: &Path, json: bool) {
+    let result = file_id::get_file_id(path);
+
+    if json {
+        print_json(path, &result);
+        return;
+    }
+
+    match result {
+        Ok(file_id) => println!("{}: {file_id:?}", path.display()),
+        Err(error) => println!("{}: Error: {error}", path.display()),
+    }
+}
+
+/// Returns true if both paths refer to the same file, or `None` if one of them can't be read
+fn compare(a: &Path, b: &Path, json: bool) -> Option<bool> {
+    let result_a = file_id::get_file_id(a);
+    let result_b = file_id::get_file_id(b);
+    let same = match (&result_a, &result_b) {
+        (Ok(a), Ok(b)) => Some(a == b),
+        _ => None,
+    };
+
+    if json {
+        print_compare_json(same, [(a, &result_a), (b, &result_b)]);
+    } else {
+        for (path, result) in [(a, &result_a), (b, &result_b)] {
+            match result {
+                Ok(file_id) => println!("{}: {file_id:?}", path.display()),
+                Err(error) => println!("{}: Error: {error}", path.display()),
+            }
+        }
+        match same {
+            Some(true) => println!("same file"),
+            Some(false) => println!("different files"),
+            None => {}
+        }
+    }
+
+    same
+}
+
+#[cfg(feature = "cli-json")]
+fn json_entry(path: &Path, result: &io::Result<FileId>) -> serde_json::Value {
+    let path = path.to_string_lossy();
+    match result {
+        Ok(file_id) => serde_json::json!({ "path": path, "id": file_id }),
+        Err(error) => serde_json::json!({ "path": path, "error": error.to_string() }),
+    }
+}
+
+#[cfg(feature = "cli-json")]
+fn print_json(path: &Path, result: &io::Result<FileId>) {
+    println!("{}", json_entry(path, result));
+}
+
+#[cfg(feature = "cli-json")]
+fn print_compare_json(same: Option<bool>, entries: [(&Path, &io::Result<FileId>); 2]) {
+    let files: Vec<_> = entries
+        .iter()
+        .map(|(path, result)| json_entry(path, result))
+        .collect();
+    println!("{}", serde_json::json!({ "same": same, "files": files }));
+}
+
+#[cfg(not(feature = "cli-json"))]
+fn print_json(_path: &Path, _result: &io::Result<FileId>) {
+    unreachable!("JSON output is rejected without the `cli-json` feature")
+}
+
+#[cfg(not(feature = "cli-json"))]
+fn print_compare_json(_same: Option<bool>, _entries: [(&Path, &io::Result<FileId>); 2]) {
+    unreachable!("JSON output is rejected without the `cli-json` feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let args = parse(&["a", "b"]).unwrap().unwrap();
+        assert_eq!(
+            args,
+            Args {
+                paths: vec!["a".into(), "b".into()],
+                ..Args::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parse_options() {
+        let args = parse(&["-r", "a"]).unwrap().unwrap();
+        assert!(args.recursive);
+
+        let args = parse(&["--compare", "a", "b"]).unwrap().unwrap();
+        assert!(args.compare);
+
+        assert_eq!(parse(&["--json", "a"]).is_ok(), cfg!(feature = "cli-json"));
+    }
+
+    #[test]
+    fn parse_help() {
+        assert_eq!(parse(&["a", "--help"]), Ok(None));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for args in [
+            &[][..],
+            &["--verbose", "a"],
+            &["-c", "a"],
+            &["-c", "a", "b", "c"],
+            &["-c", "-r", "a", "b"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn compare_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a");
+        let b = dir.path().join("b");
+        fs::write(&a, b"a").unwrap();
+        fs::write(&b, b"b").unwrap();
+
+        assert_eq!(compare(&a, &a, false), Some(true));
+        assert_eq!(compare(&a, &b, false), Some(false));
+        assert_eq!(compare(&a, &dir.path().join("missing"), false), None);
+    }
+}
@@ -6,6 +6,8 @@
 //! a file or directory can be uniquely identified on a single computer at a given time.
 //!
 //! Keep in mind though, that IDs may be re-used at some point.
+//! A [`FileIdentity`] additionally captures the inode generation and birth time where available,
+//! which tells a re-used ID apart from the same file.
 //!
 //! ## Example
 //!
@@ -27,7 +29,7 @@
 //! let file_id = file_id::get_high_res_file_id(file.path()).unwrap();
 //! println!("{file_id:?}");
 //! ```
-use std::{fs, io, path::Path};
+use std::{fs, io, path::Path, time::SystemTime};
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -104,16 +106,275 @@ impl FileId {
             file_id,
         }
     }
+
+    /// Get the `FileId` from already retrieved metadata, without accessing the file system
+    #[cfg(target_family = "unix")]
+    pub fn from_metadata(metadata: &fs::Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+
+        FileId::new_inode(metadata.dev(), metadata.ino())
+    }
+}
+
+/// Extended identity of a file
+///
+/// Besides the [`FileId`], it captures properties that change when a file ID is re-used for a new
+/// file, and properties that change when the file is written to.
+///
+/// ```
+/// use file_id::{get_file_identity, IdentityMatch};
+///
+/// let file = tempfile::NamedTempFile::new().unwrap();
+///
+/// let before = get_file_identity(file.path()).unwrap();
+/// std::fs::write(file.path(), b"Lorem ipsum").unwrap();
+/// let after = get_file_identity(file.path()).unwrap();
+///
+/// assert_eq!(before.compare(&after), IdentityMatch::Same);
+/// assert!(!before.is_unchanged(&after));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct FileIdentity {
+    /// File ID
+    pub file_id: FileId,
+
+    /// Inode generation, available on Linux for file systems that support `FS_IOC_GETVERSION`,
+    /// e.g. ext4 and XFS.
+    ///
+    /// Reading it requires opening the file, see [`get_file_identity`].
+    pub generation: Option<u32>,
+
+    /// Creation time, available on Windows, MacOS and on Linux for file systems that support
+    /// `statx`.
+    pub birth_time: Option<SystemTime>,
+
+    /// Last modification time
+    pub modified: Option<SystemTime>,
+
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// The result of comparing two [`FileIdentity`]s
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdentityMatch {
+    /// Both identities belong to the same file, as far as can be told.
+    Same,
+
+    /// The file ID was re-used for a different file.
+    Reused,
+
+    /// The file IDs differ.
+    Different,
+}
+
+impl FileIdentity {
+    /// Compare with an identity of a possibly different file
+    ///
+    /// Equal file IDs are only considered `Reused` if the generation or birth time is known for
+    /// both identities and differs. The size and modification time are not taken into account, as
+    /// they change when the file is written to.
+    pub fn compare(&self, other: &Self) -> IdentityMatch {
+        fn differs<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+
+        if self.file_id != other.file_id {
+            IdentityMatch::Different
+        } else if differs(self.generation, other.generation)
+            || differs(self.birth_time, other.birth_time)
+        {
+            IdentityMatch::Reused
+        } else {
+            IdentityMatch::Same
+        }
+    }
+
+    /// Returns true if both identities belong to the same file, which wasn't modified in between
+    pub fn is_unchanged(&self, other: &Self) -> bool {
+        self.compare(other) == IdentityMatch::Same
+            && self.size == other.size
+            && self.modified == other.modified
+    }
+}
+
+/// Get the `FileIdentity` for the file or directory at `path`
+///
+/// On Linux, regular files and directories are opened read-only to read the inode generation. A
+/// watcher reports this as an open and a close event, e.g. `IN_OPEN` and `IN_CLOSE_NOWRITE` with
+/// inotify. The access time is only kept if the process owns the file, otherwise it may be updated
+/// depending on the mount options.
+#[cfg(target_family = "unix")]
+pub fn get_file_identity(path: impl AsRef<Path>) -> io::Result<FileIdentity> {
+    let path = path.as_ref();
+    let metadata = fs::metadata(path)?;
+
+    // opening other file types, e.g. devices, may have side effects
+    let generation = if metadata.is_file() || metadata.is_dir() {
+        get_generation(path)
+    } else {
+        None
+    };
+
+    Ok(FileIdentity {
+        file_id: FileId::from_metadata(&metadata),
+        generation,
+        birth_time: metadata.created().ok(),
+        modified: metadata.modified().ok(),
+        size: metadata.len(),
+    })
+}
+
+/// Get the `FileIdentity` for the file or directory at `path`
+#[cfg(target_family = "windows")]
+pub fn get_file_identity(path: impl AsRef<Path>) -> io::Result<FileIdentity> {
+    let file = open_file(path)?;
+    let file_id = get_file_id_from_file(&file)?;
+    let metadata = file.metadata()?;
+
+    Ok(FileIdentity {
+        file_id,
+        generation: None,
+        birth_time: metadata.created().ok(),
+        modified: metadata.modified().ok(),
+        size: metadata.len(),
+    })
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64",
+        target_arch = "riscv64"
+    )
+))]
+fn get_generation(path: &Path) -> Option<u32> {
+    use std::{
+        fs::OpenOptions,
+        os::{
+            raw::{c_int, c_long},
+            unix::prelude::*,
+        },
+    };
+
+    // _IOR('v', 1, long)
+    const FS_IOC_GETVERSION: u32 =
+        (2 << 30) | ((std::mem::size_of::<c_long>() as u32) << 16) | ((b'v' as u32) << 8) | 1;
+
+    // `O_PATH` can't be used, the ioctl needs a file opened for reading
+    let open = |flags| {
+        OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK | libc::O_NOCTTY | flags)
+            .open(path)
+    };
+    // `O_NOATIME` is only permitted for the owner of the file
+    let file = open(libc::O_NOATIME).or_else(|_| open(0)).ok()?;
+
+    // file systems write an int, despite the declared type
+    let mut generation: c_int = 0;
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_GETVERSION as _, &mut generation) };
+    (ret == 0).then(|| generation as u32)
+}
+
+#[cfg(all(
+    target_family = "unix",
+    not(all(
+        target_os = "linux",
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "arm",
+            target_arch = "aarch64",
+            target_arch = "riscv64"
+        )
+    ))
+))]
+fn get_generation(_path: &Path) -> Option<u32> {
+    None
 }
 
 /// Get the `FileId` for the file or directory at `path`
 #[cfg(target_family = "unix")]
 pub fn get_file_id(path: impl AsRef<Path>) -> io::Result<FileId> {
-    use std::os::unix::fs::MetadataExt;
-
     let metadata = fs::metadata(path.as_ref())?;
 
-    Ok(FileId::new_inode(metadata.dev(), metadata.ino()))
+    Ok(FileId::from_metadata(&metadata))
+}
+
+/// Get the `FileId` for an open file or directory
+///
+/// Unlike [`get_file_id`], the result refers to the opened file, even if it was renamed or
+/// replaced in the meantime.
+///
+/// ```
+/// let file = tempfile::NamedTempFile::new().unwrap();
+///
+/// let file_id = file_id::get_file_id_from_file(file.as_file()).unwrap();
+/// assert_eq!(file_id, file_id::get_file_id(file.path()).unwrap());
+/// ```
+#[cfg(target_family = "unix")]
+pub fn get_file_id_from_file(file: &fs::File) -> io::Result<FileId> {
+    let metadata = file.metadata()?;
+
+    Ok(FileId::from_metadata(&metadata))
+}
+
+/// Get the `FileId` for an open file or directory
+///
+/// Unlike [`get_file_id`], the result refers to the opened file, even if it was renamed or
+/// replaced in the meantime.
+///
+/// ```
+/// let file = tempfile::NamedTempFile::new().unwrap();
+///
+/// let file_id = file_id::get_file_id_from_file(file.as_file()).unwrap();
+/// assert_eq!(file_id, file_id::get_file_id(file.path()).unwrap());
+/// ```
+#[cfg(target_family = "windows")]
+pub fn get_file_id_from_file(file: &fs::File) -> io::Result<FileId> {
+    unsafe { get_file_info_ex(file).or_else(|_| get_file_info(file)) }
+}
+
+/// Get the `FileId` for `path` relative to the directory `dir_fd` (`fstatat`)
+///
+/// If `path` is absolute, `dir_fd` is ignored. Symbolic links are followed, like with
+/// [`get_file_id`].
+///
+/// ```
+/// use std::{fs::File, os::unix::io::AsRawFd};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// std::fs::write(dir.path().join("file"), b"").unwrap();
+///
+/// let dir_file = File::open(dir.path()).unwrap();
+/// let file_id = file_id::get_file_id_at(dir_file.as_raw_fd(), "file").unwrap();
+/// assert_eq!(file_id, file_id::get_file_id(dir.path().join("file")).unwrap());
+/// ```
+#[cfg(target_family = "unix")]
+#[allow(clippy::unnecessary_cast)]
+pub fn get_file_id_at(
+    dir_fd: std::os::unix::io::RawFd,
+    path: impl AsRef<Path>,
+) -> io::Result<FileId> {
+    use std::{ffi::CString, mem, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_ref().as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    let ret = unsafe { libc::fstatat(dir_fd, path.as_ptr(), &mut stat, 0) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // the field types differ between platforms
+    Ok(FileId::new_inode(stat.st_dev as u64, stat.st_ino as u64))
 }
 
 /// Get the `FileId` for the file or directory at `path`
@@ -140,6 +401,157 @@ pub fn get_high_res_file_id(path: impl AsRef<Path>) -> io::Result<FileId> {
     unsafe { get_file_info(&file) }
 }
 
+/// Persistent handle of a file, available on Linux
+///
+/// Unlike inode numbers, file handles stay valid on file systems that don't have stable inode
+/// numbers, e.g. NFS, and can be used to reopen the file with [`FileHandle::open`].
+/// See `name_to_handle_at(2)` for details.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FileHandle {
+    /// File handle, unique within the file system.
+    #[cfg_attr(feature = "serde", serde(rename = "handle"))]
+    Handle {
+        /// Device ID of the file system
+        #[cfg_attr(feature = "serde", serde(rename = "device"))]
+        device_id: u64,
+
+        /// Type of the handle, specific to the file system
+        #[cfg_attr(feature = "serde", serde(rename = "type"))]
+        handle_type: i32,
+
+        /// Opaque handle data
+        #[cfg_attr(feature = "serde", serde(rename = "bytes"))]
+        handle: Vec<u8>,
+    },
+
+    /// The file system doesn't support file handles, the file ID is used instead.
+    #[cfg_attr(feature = "serde", serde(rename = "fileid"))]
+    FileId(FileId),
+}
+
+#[cfg(target_os = "linux")]
+const MAX_HANDLE_SZ: usize = 128;
+
+/// `struct file_handle`, with room for the largest possible handle
+#[cfg(target_os = "linux")]
+#[repr(C)]
+struct RawFileHandle {
+    handle_bytes: std::os::raw::c_uint,
+    handle_type: std::os::raw::c_int,
+    f_handle: [u8; MAX_HANDLE_SZ],
+}
+
+#[cfg(target_os = "linux")]
+impl FileHandle {
+    /// Reopen the file read-only
+    ///
+    /// `mount` can be any file or directory on the same file system, e.g. the root of a watched
+    /// directory. This requires the `CAP_DAC_READ_SEARCH` capability. The `FileId` variant can't
+    /// be opened and returns an error of kind [`io::ErrorKind::Unsupported`].
+    pub fn open(&self, mount: &fs::File) -> io::Result<fs::File> {
+        use std::os::unix::prelude::*;
+
+        let (handle_type, handle) = match self {
+            FileHandle::Handle {
+                handle_type,
+                handle,
+                ..
+            } => (*handle_type, handle),
+            FileHandle::FileId(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "the file system doesn't support file handles",
+                ))
+            }
+        };
+        if handle.len() > MAX_HANDLE_SZ {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "file handle is too large",
+            ));
+        }
+
+        let mut raw = RawFileHandle {
+            handle_bytes: handle.len() as _,
+            handle_type,
+            f_handle: [0; MAX_HANDLE_SZ],
+        };
+        raw.f_handle[..handle.len()].copy_from_slice(handle);
+
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_open_by_handle_at,
+                mount.as_raw_fd(),
+                &mut raw as *mut RawFileHandle,
+                libc::O_RDONLY | libc::O_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(unsafe { fs::File::from_raw_fd(fd as RawFd) })
+    }
+}
+
+/// Get the `FileHandle` for the file or directory at `path`
+///
+/// Falls back to the `FileId` if the file system doesn't support file handles.
+///
+/// ```
+/// let file = tempfile::NamedTempFile::new().unwrap();
+///
+/// let handle = file_id::get_file_handle(file.path()).unwrap();
+/// assert_eq!(handle, file_id::get_file_handle(file.path()).unwrap());
+/// println!("{handle:?}");
+/// ```
+#[cfg(target_os = "linux")]
+pub fn get_file_handle(path: impl AsRef<Path>) -> io::Result<FileHandle> {
+    use std::{fs::OpenOptions, os::unix::prelude::*};
+
+    // the file is only opened to refer to it, which doesn't require any permissions
+    let file = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH)
+        .open(path)?;
+    let metadata = file.metadata()?;
+
+    let mut raw = RawFileHandle {
+        handle_bytes: MAX_HANDLE_SZ as _,
+        handle_type: 0,
+        f_handle: [0; MAX_HANDLE_SZ],
+    };
+    let mut mount_id: std::os::raw::c_int = 0;
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_name_to_handle_at,
+            file.as_raw_fd(),
+            b"\0".as_ptr(),
+            &mut raw as *mut RawFileHandle,
+            &mut mount_id as *mut std::os::raw::c_int,
+            libc::AT_EMPTY_PATH,
+        )
+    };
+    if ret != 0 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            // seccomp filters, e.g. of container runtimes, may deny the syscall with EPERM
+            Some(libc::EOPNOTSUPP | libc::ENOSYS | libc::EOVERFLOW | libc::EPERM) => {
+                Ok(FileHandle::FileId(FileId::from_metadata(&metadata)))
+            }
+            _ => Err(error),
+        };
+    }
+
+    Ok(FileHandle::Handle {
+        device_id: metadata.dev(),
+        handle_type: raw.handle_type,
+        handle: raw.f_handle[..raw.handle_bytes as usize].to_vec(),
+    })
+}
+
 #[cfg(target_family = "windows")]
 unsafe fn get_file_info_ex(file: &fs::File) -> Result<FileId, io::Error> {
     use std::{mem, os::windows::prelude::*};
@@ -196,3 +608,85 @@ fn open_file<P: AsRef<Path>>(path: P) -> io::Result<fs::File> {
         .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
         .open(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn identity(inode: u64, generation: Option<u32>, birth_secs: Option<u64>) -> FileIdentity {
+        FileIdentity {
+            file_id: FileId::new_inode(1, inode),
+            generation,
+            birth_time: birth_secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            modified: None,
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn compare_identities() {
+        let file = identity(1, Some(1), Some(10));
+
+        assert_eq!(file.compare(&file), IdentityMatch::Same);
+        assert_eq!(file.compare(&identity(1, None, None)), IdentityMatch::Same);
+        assert_eq!(
+            file.compare(&identity(1, Some(2), Some(10))),
+            IdentityMatch::Reused
+        );
+        assert_eq!(
+            file.compare(&identity(1, None, Some(20))),
+            IdentityMatch::Reused
+        );
+        assert_eq!(
+            file.compare(&identity(2, Some(1), Some(10))),
+            IdentityMatch::Different
+        );
+    }
+
+    #[test]
+    fn unchanged_identities() {
+        let file = identity(1, Some(1), Some(10));
+        let written = FileIdentity { size: 1, ..file };
+
+        assert!(file.is_unchanged(&file));
+        assert!(!file.is_unchanged(&written));
+        assert!(!file.is_unchanged(&identity(1, Some(2), Some(10))));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn get_file_id_at_rejects_nul() {
+        use std::os::unix::io::AsRawFd;
+
+        let dir = fs::File::open(".").unwrap();
+        let err = get_file_id_at(dir.as_raw_fd(), "file\0name").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "serde"))]
+    #[test]
+    fn file_handle_serde() {
+        let handles = [
+            FileHandle::Handle {
+                device_id: 1,
+                handle_type: 2,
+                handle: vec![3, 4],
+            },
+            FileHandle::FileId(FileId::new_inode(1, 2)),
+        ];
+        for handle in handles {
+            let json = serde_json::to_string(&handle).unwrap();
+            assert_eq!(serde_json::from_str::<FileHandle>(&json).unwrap(), handle);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn file_id_handle_cant_be_opened() {
+        let mount = fs::File::open(".").unwrap();
+        let handle = FileHandle::FileId(FileId::new_inode(1, 2));
+        let err = handle.open(&mount).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
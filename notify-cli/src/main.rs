@@ -0,0 +1,438 @@
+//! Command line tool to watch paths and print the events of notify
+
+use std::{
+    ffi::OsString,
+    path::PathBuf,
+    process::{self, Command},
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use notify::{event::format, Config, Event, PollWatcher, RecursiveMode, Watcher, WatcherKind};
+use notify_debouncer_full::FileIdMap;
+
+const USAGE: &str = "\
+Usage: notify [options] <path>...
+
+Options:
+  -w, --watcher <kind>      Backend: recommended (default), poll, inotify, fsevent, kqueue, windows
+  -n, --non-recursive       Only watch the given paths, not their subdirectories
+  -i, --poll-interval <ms>  Interval of the poll backend
+  -d, --debounce <mode>     Debouncer: none (default), mini, full (default with --exec)
+  -t, --timeout <ms>        Debounce timeout, 500 by default
+  -j, --json                Print the events as JSON Lines
+  -e, --exec <command>      Run a command with the shell after each batch of debounced events,
+                            the changed paths are passed in NOTIFY_PATHS, one per line
+  -h, --help                Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Debounce {
+    None,
+    Mini,
+    Full,
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    /// `None` selects the recommended watcher
+    watcher: Option<WatcherKind>,
+    recursive_mode: RecursiveMode,
+    poll_interval: Option<Duration>,
+    debounce: Debounce,
+    timeout: Duration,
+    json: bool,
+    exec: Option<String>,
+    paths: Vec<PathBuf>,
+}
+
+/// An event of the watcher or one of the debouncers, formatted when it is printed
+enum Entry {
+    Event(Event),
+    Mini(notify_debouncer_mini::DebouncedEvent),
+}
+
+impl Entry {
+    fn paths(&self) -> &[PathBuf] {
+        match self {
+            Entry::Event(event) => &event.paths,
+            Entry::Mini(event) => std::slice::from_ref(&event.path),
+        }
+    }
+
+    /// Format the entry as a single line, JSON fails for paths that aren't valid UTF-8
+    fn format(&self, json: bool) -> notify::Result<String> {
+        match self {
+            Entry::Event(event) if json => format::to_json_line(event),
+            Entry::Event(event) => Ok(format::to_text(event)),
+            Entry::Mini(event) if json => serde_json::to_string(event)
+                .map_err(|error| notify::Error::generic(&error.to_string())),
+            Entry::Mini(event) => {
+                let path = format::path_to_text(&event.path);
+                Ok(match event.change {
+                    Some(change) => format!("{:?}({:?}) {}", event.kind, change, path),
+                    None => format!("{:?} {}", event.kind, path),
+                })
+            }
+        }
+    }
+}
+
+type Batch = Result<Vec<Entry>, Vec<notify::Error>>;
+
+fn join(items: &[impl std::fmt::Display], separator: &str) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn parse_millis(value: Option<String>, option: &str) -> Result<Duration, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("{option} takes a number of milliseconds"))
+}
+
+/// Returns `None` if the help was requested
+fn parse_args(raw_args: impl IntoIterator<Item = OsString>) -> Result<Option<Args>, String> {
+    let mut args = Args {
+        watcher: None,
+        recursive_mode: RecursiveMode::Recursive,
+        poll_interval: None,
+        debounce: Debounce::None,
+        timeout: Duration::from_millis(500),
+        json: false,
+        exec: None,
+        paths: Vec::new(),
+    };
+    let mut debounce = None;
+
+    let mut raw_args = raw_args.into_iter();
+    while let Some(arg) = raw_args.next() {
+        let mut value = || raw_args.next().and_then(|value| value.into_string().ok());
+        match arg.to_str() {
+            Some("-w" | "--watcher") => {
+                args.watcher = match value().as_deref() {
+                    Some("recommended") => None,
+                    Some("poll") => Some(WatcherKind::PollWatcher),
+                    Some("inotify") => Some(WatcherKind::Inotify),
+                    Some("fsevent") => Some(WatcherKind::Fsevent),
+                    Some("kqueue") => Some(WatcherKind::Kqueue),
+                    Some("windows") => Some(WatcherKind::ReadDirectoryChangesWatcher),
+                    _ => return Err("unknown watcher".to_string()),
+                }
+            }
+            Some("-n" | "--non-recursive") => args.recursive_mode = RecursiveMode::NonRecursive,
+            Some("-i" | "--poll-interval") => {
+                args.poll_interval = Some(parse_millis(value(), "--poll-interval")?)
+            }
+            Some("-d" | "--debounce") => {
+                debounce = match value().as_deref() {
+                    Some("none") => Some(Debounce::None),
+                    Some("mini") => Some(Debounce::Mini),
+                    Some("full") => Some(Debounce::Full),
+                    _ => return Err("unknown debouncer".to_string()),
+                }
+            }
+            Some("-t" | "--timeout") => args.timeout = parse_millis(value(), "--timeout")?,
+            Some("-j" | "--json") => args.json = true,
+            Some("-e" | "--exec") => {
+                args.exec = Some(value().ok_or("--exec takes a command")?);
+            }
+            Some("-h" | "--help") => return Ok(None),
+            Some(flag) if flag.starts_with('-') => return Err(format!("unknown option {flag}")),
+            _ => args.paths.push(arg.into()),
+        }
+    }
+
+    if args.paths.is_empty() {
+        return Err("no path given".to_string());
+    }
+
+    // every raw event would run the command, which loops if the command changes a watched file
+    args.debounce = match (debounce, &args.exec) {
+        (Some(Debounce::None), Some(_)) => {
+            return Err("--exec requires a debouncer".to_string());
+        }
+        (Some(debounce), _) => debounce,
+        (None, Some(_)) => Debounce::Full,
+        (None, None) => Debounce::None,
+    };
+
+    Ok(Some(args))
+}
+
+fn main() {
+    let args = match parse_args(std::env::args_os().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(error) => {
+            eprintln!("Error: {error}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = run(&args) {
+        eprintln!("Error: {error}");
+        process::exit(1);
+    }
+}
+
+/// Select the backend
+fn run(args: &Args) -> notify::Result<()> {
+    match args.watcher {
+        None => watch::<notify::RecommendedWatcher>(args),
+        Some(WatcherKind::PollWatcher) => watch::<PollWatcher>(args),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Some(WatcherKind::Inotify) => watch::<notify::INotifyWatcher>(args),
+        #[cfg(all(target_os = "macos", not(feature = "macos_kqueue")))]
+        Some(WatcherKind::Fsevent) => watch::<notify::FsEventWatcher>(args),
+        #[cfg(any(
+            target_os = "freebsd",
+            target_os = "openbsd",
+            target_os = "netbsd",
+            target_os = "dragonfly",
+            target_os = "ios",
+            all(target_os = "macos", feature = "macos_kqueue")
+        ))]
+        Some(WatcherKind::Kqueue) => watch::<notify::KqueueWatcher>(args),
+        #[cfg(target_os = "windows")]
+        Some(WatcherKind::ReadDirectoryChangesWatcher) => {
+            watch::<notify::ReadDirectoryChangesWatcher>(args)
+        }
+        Some(kind) => Err(notify::Error::generic(&format!(
+            "the {kind:?} backend isn't available on this platform"
+        ))),
+    }
+}
+
+/// Watch the paths with the selected debouncer and print the events until interrupted
+fn watch<W: Watcher + 'static>(args: &Args) -> notify::Result<()> {
+    let (tx, rx): (Sender<Batch>, Receiver<Batch>) = mpsc::channel();
+    let mut config = Config::default();
+    if let Some(poll_interval) = args.poll_interval {
+        config = config.with_poll_interval(poll_interval);
+    }
+
+    match args.debounce {
+        Debounce::None => {
+            let mut watcher = W::new(
+                move |result: notify::Result<Event>| {
+                    let batch = result
+                        .map(|event| vec![Entry::Event(event)])
+                        .map_err(|error| vec![error]);
+                    let _ = tx.send(batch);
+                },
+                config,
+            )?;
+            for path in &args.paths {
+                watcher.watch(path, args.recursive_mode)?;
+            }
+            print_batches(rx, args);
+        }
+        Debounce::Mini => {
+            let mut debouncer = notify_debouncer_mini::new_debouncer_opt::<_, W>(
+                notify_debouncer_mini::Config::default()
+                    .with_timeout(args.timeout)
+                    .with_change_kinds(true)
+                    .with_notify_config(config),
+                move |result: notify_debouncer_mini::DebounceEventResult| {
+                    let batch = result.map(|events| events.into_iter().map(Entry::Mini).collect());
+                    let _ = tx.send(batch);
+                },
+            )?;
+            for path in &args.paths {
+                debouncer.watcher().watch(path, args.recursive_mode)?;
+            }
+            print_batches(rx, args);
+        }
+        Debounce::Full => {
+            let mut debouncer = notify_debouncer_full::new_debouncer_with_config::<_, W, _>(
+                notify_debouncer_full::Config::default()
+                    .with_timeout(args.timeout)
+                    .with_notify_config(config),
+                move |result: notify_debouncer_full::DebounceEventResult| {
+                    let batch = result.map(|events| {
+                        events
+                            .into_iter()
+                            .map(|event| Entry::Event(event.event))
+                            .collect()
+                    });
+                    let _ = tx.send(batch);
+                },
+                FileIdMap::new(),
+            )?;
+            for path in &args.paths {
+                debouncer.watch(path, args.recursive_mode)?;
+            }
+            print_batches(rx, args);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_batches(rx: Receiver<Batch>, args: &Args) {
+    for batch in rx {
+        match batch {
+            Ok(entries) if entries.is_empty() => {}
+            Ok(entries) => {
+                for entry in &entries {
+                    match entry.format(args.json) {
+                        Ok(line) => println!("{line}"),
+                        Err(error) => eprintln!("Error: {error}"),
+                    }
+                }
+                if let Some(command) = &args.exec {
+                    // mini `Rescan` events come with an empty path
+                    let mut paths: Vec<_> = entries
+                        .iter()
+                        .flat_map(|entry| entry.paths())
+                        .filter(|path| !path.as_os_str().is_empty())
+                        .collect();
+                    paths.sort();
+                    paths.dedup();
+                    let paths: Vec<_> = paths.iter().map(|path| path.display()).collect();
+                    run_command(command, &join(&paths, "\n"));
+                }
+            }
+            Err(errors) => {
+                for error in errors {
+                    eprintln!("Error: {error}");
+                }
+            }
+        }
+    }
+}
+
+fn run_command(command: &str, paths: &str) {
+    #[cfg(target_family = "windows")]
+    let mut shell = {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    };
+    #[cfg(not(target_family = "windows"))]
+    let mut shell = {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    match shell.arg(command).env("NOTIFY_PATHS", paths).status() {
+        Ok(status) if !status.success() => eprintln!("Command failed: {status}"),
+        Ok(_) => {}
+        Err(error) => eprintln!("Failed to run command: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn parse_defaults() {
+        let args = parse(&["src"]).unwrap().unwrap();
+        assert_eq!(
+            args,
+            Args {
+                watcher: None,
+                recursive_mode: RecursiveMode::Recursive,
+                poll_interval: None,
+                debounce: Debounce::None,
+                timeout: Duration::from_millis(500),
+                json: false,
+                exec: None,
+                paths: vec!["src".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_options() {
+        let args = parse(&[
+            "-w",
+            "poll",
+            "-n",
+            "-i",
+            "100",
+            "--debounce",
+            "full",
+            "-t",
+            "50",
+            "--json",
+            "-e",
+            "make",
+            "a",
+            "b",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            args,
+            Args {
+                watcher: Some(WatcherKind::PollWatcher),
+                recursive_mode: RecursiveMode::NonRecursive,
+                poll_interval: Some(Duration::from_millis(100)),
+                debounce: Debounce::Full,
+                timeout: Duration::from_millis(50),
+                json: true,
+                exec: Some("make".to_string()),
+                paths: vec!["a".into(), "b".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn parse_exec() {
+        let args = parse(&["-e", "make", "src"]).unwrap().unwrap();
+        assert_eq!(args.debounce, Debounce::Full);
+
+        let args = parse(&["-e", "make", "-d", "mini", "src"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.debounce, Debounce::Mini);
+
+        assert!(parse(&["-e", "make", "-d", "none", "src"]).is_err());
+    }
+
+    #[test]
+    fn parse_help() {
+        assert_eq!(parse(&["src", "--help"]), Ok(None));
+    }
+
+    #[test]
+    fn parse_errors() {
+        for args in [
+            &[][..],
+            &["--verbose", "src"],
+            &["-w", "fanotify", "src"],
+            &["-d", "max", "src"],
+            &["-t", "soon", "src"],
+            &["-i"],
+            &["src", "-e"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn format_non_utf8_path() {
+        use notify::event::{CreateKind, EventKind};
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = PathBuf::from(OsString::from_vec(b"a\xff".to_vec()));
+        let entry = Entry::Event(Event::new(EventKind::Create(CreateKind::File)).add_path(path));
+        assert_eq!(entry.format(false).unwrap(), r#"create(file) "a\xff""#);
+        assert!(entry.format(true).is_err());
+    }
+}
@@ -4,7 +4,11 @@ use std::{
 };
 
 use file_id::{get_file_id, FileId};
+#[cfg(any(test, feature = "memory"))]
+use notify::memory::MemoryFs;
 use notify::RecursiveMode;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
 /// The interface of a file ID cache.
@@ -26,10 +30,40 @@ pub trait FileIdCache {
     /// This will be called if a file or directory is deleted.
     fn remove_path(&mut self, path: &Path);
 
+    /// Move a path and all its children to a new path.
+    ///
+    /// This will be called if a rename event could be matched. The file IDs don't change on a
+    /// rename, so an implementation can move the cached IDs instead of reading them again, which
+    /// keeps the IDs below a renamed directory available for matching later renames.
+    ///
+    /// The default implementation removes `from` and adds `to`.
+    fn rename_path(&mut self, from: &Path, to: &Path) {
+        self.remove_path(from);
+        self.add_path(to);
+    }
+
     /// Re-scan all paths.
     ///
     /// This will be called if the notification back-end has dropped events.
     fn rescan(&mut self);
+
+    /// Add a watched path to the cache.
+    ///
+    /// This will be called by [`Debouncer::watch`](crate::Debouncer::watch). The default
+    /// implementation adds the path, an implementation that keeps track of its roots should
+    /// take the recursive mode into account.
+    fn add_root(&mut self, path: &Path, recursive_mode: RecursiveMode) {
+        let _ = recursive_mode;
+        self.add_path(path);
+    }
+
+    /// Remove a watched path from the cache.
+    ///
+    /// This will be called by [`Debouncer::unwatch`](crate::Debouncer::unwatch). The default
+    /// implementation removes the path.
+    fn remove_root(&mut self, path: &Path) {
+        self.remove_path(path);
+    }
 }
 
 /// A cache to hold the file system IDs of all watched files.
@@ -85,28 +119,12 @@ impl FileIdCache for FileIdMap {
     }
 
     fn add_path(&mut self, path: &Path) {
-        let is_recursive = self
-            .roots
-            .iter()
-            .find_map(|(root, recursive_mode)| {
-                if path.starts_with(root) {
-                    Some(*recursive_mode == RecursiveMode::Recursive)
-                } else {
-                    None
-                }
-            })
-            .unwrap_or_default();
-
-        for (path, file_id) in WalkDir::new(path)
-            .follow_links(true)
-            .max_depth(Self::dir_scan_depth(is_recursive))
-            .into_iter()
-            .filter_map(|entry| {
-                let path = entry.ok()?.into_path();
-                let file_id = get_file_id(&path).ok()?;
-                Some((path, file_id))
-            })
-        {
+        let is_recursive = matches!(
+            innermost_root(&self.roots, path),
+            Some(RecursiveMode::Recursive)
+        );
+
+        for (path, file_id) in scan_file_ids(path, Self::dir_scan_depth(is_recursive)) {
             self.paths.insert(path, file_id);
         }
     }
@@ -115,16 +133,393 @@ impl FileIdCache for FileIdMap {
         self.paths.retain(|p, _| !p.starts_with(path));
     }
 
+    fn rename_path(&mut self, from: &Path, to: &Path) {
+        if !rename_paths(&mut self.paths, from, to) {
+            self.add_path(to);
+        }
+    }
+
     fn rescan(&mut self) {
         for (root, _) in self.roots.clone() {
             self.add_path(&root);
         }
     }
+
+    fn add_root(&mut self, path: &Path, recursive_mode: RecursiveMode) {
+        FileIdMap::add_root(self, path, recursive_mode);
+    }
+
+    fn remove_root(&mut self, path: &Path) {
+        FileIdMap::remove_root(self, path);
+    }
+}
+
+/// A cache like [`FileIdMap`] that can be saved and loaded with serde.
+///
+/// Scanning a large tree for file IDs can take a long time. This cache can be serialized when the
+/// program exits and deserialized on the next start, so that [`add_root`](Self::add_root) doesn't
+/// have to scan the roots that were already known.
+///
+/// The loaded file IDs are validated lazily: an entry is only read again from disk when the
+/// debouncer adds its path, e.g. because of a create or rename event, or when it is validated
+/// explicitly with [`validate`](Self::validate). If the file ID of a loaded entry is found at
+/// another path while validating, the file was renamed while the program wasn't running. These
+/// renames are returned by `validate` and collected for [`take_offline_renames`](Self::take_offline_renames).
+///
+/// # Example
+///
+/// ```rust
+/// # use std::{fs::File, path::Path};
+/// use notify_debouncer_full::{notify::RecursiveMode, PersistentFileIdMap};
+///
+/// # let dir = tempfile::tempdir().unwrap();
+/// # let (root, cache_file) = (dir.path(), dir.path().join("cache.json"));
+/// let mut cache = File::open(&cache_file)
+///     .ok()
+///     .and_then(|file| serde_json::from_reader(file).ok())
+///     .unwrap_or_else(PersistentFileIdMap::new);
+/// cache.add_root(root, RecursiveMode::Recursive);
+///
+/// // ... use the cache with a debouncer and get it back with `Debouncer::cache` ...
+///
+/// serde_json::to_writer(File::create(&cache_file).unwrap(), &cache).unwrap();
+/// ```
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "SavedFileIdMap")]
+pub struct PersistentFileIdMap {
+    paths: HashMap<PathBuf, FileId>,
+    /// The roots and whether they are recursive
+    roots: Vec<(PathBuf, bool)>,
+    /// The loaded entries that weren't read from disk again yet, by file ID, hard links share one
+    #[serde(skip)]
+    unvalidated: HashMap<FileId, Vec<PathBuf>>,
+    #[serde(skip)]
+    offline_renames: Vec<(PathBuf, PathBuf)>,
+}
+
+/// The serialized fields of a [`PersistentFileIdMap`]
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct SavedFileIdMap {
+    paths: HashMap<PathBuf, FileId>,
+    roots: Vec<(PathBuf, bool)>,
+}
+
+#[cfg(feature = "serde")]
+impl From<SavedFileIdMap> for PersistentFileIdMap {
+    fn from(saved: SavedFileIdMap) -> Self {
+        let mut unvalidated: HashMap<FileId, Vec<PathBuf>> = HashMap::new();
+        for (path, file_id) in &saved.paths {
+            unvalidated.entry(*file_id).or_default().push(path.clone());
+        }
+        Self {
+            paths: saved.paths,
+            roots: saved.roots,
+            unvalidated,
+            offline_renames: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl PersistentFileIdMap {
+    /// Construct an empty cache.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a path to the cache, see [`FileIdMap::add_root`].
+    ///
+    /// If the root was loaded with the same recursive mode, it isn't scanned again.
+    pub fn add_root(&mut self, path: impl Into<PathBuf>, recursive_mode: RecursiveMode) {
+        let path = path.into();
+        let is_recursive = recursive_mode == RecursiveMode::Recursive;
+
+        if self.roots.contains(&(path.clone(), is_recursive)) {
+            return;
+        }
+
+        self.roots.push((path.clone(), is_recursive));
+
+        self.add_path(&path);
+    }
+
+    /// Remove a path form the cache, see [`FileIdMap::remove_root`].
+    pub fn remove_root(&mut self, path: impl AsRef<Path>) {
+        self.roots.retain(|(root, _)| !root.starts_with(&path));
+
+        self.remove_path(path.as_ref());
+    }
+
+    /// Read the file IDs of `path` and its children from disk, replacing the loaded ones.
+    ///
+    /// Returns the renames that were detected, as `(from, to)` pairs. They are also collected for
+    /// [`take_offline_renames`](Self::take_offline_renames).
+    pub fn validate(&mut self, path: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let count = self.offline_renames.len();
+        self.add_path(path);
+        self.offline_renames[count..].to_vec()
+    }
+
+    /// Returns whether the file ID of `path` was read from disk since the cache was loaded.
+    pub fn is_validated(&self, path: &Path) -> bool {
+        match self.paths.get(path) {
+            Some(file_id) => !matches!(
+                self.unvalidated.get(file_id),
+                Some(paths) if paths.iter().any(|p| p == path)
+            ),
+            None => false,
+        }
+    }
+
+    /// Take the renames detected while validating loaded file IDs, as `(from, to)` pairs.
+    pub fn take_offline_renames(&mut self) -> Vec<(PathBuf, PathBuf)> {
+        std::mem::take(&mut self.offline_renames)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl FileIdCache for PersistentFileIdMap {
+    fn cached_file_id(&self, path: &Path) -> Option<&FileId> {
+        self.paths.get(path)
+    }
+
+    fn add_path(&mut self, path: &Path) {
+        let is_recursive = matches!(innermost_root(&self.roots, path), Some(true));
+
+        let depth = FileIdMap::dir_scan_depth(is_recursive);
+        for (p, file_id) in scan_file_ids(path, depth) {
+            if let Some(loaded) = self.unvalidated.get_mut(&file_id) {
+                if let Some(index) = loaded.iter().position(|from| *from == p) {
+                    loaded.swap_remove(index);
+                } else if let Some(index) = loaded.iter().position(|from| !from.exists()) {
+                    // a loaded entry with the same file ID at another path was renamed
+                    let from = loaded.swap_remove(index);
+                    self.paths.remove(&from);
+                    self.offline_renames.push((from, p.clone()));
+                }
+                if loaded.is_empty() {
+                    self.unvalidated.remove(&file_id);
+                }
+            }
+            self.paths.insert(p, file_id);
+        }
+
+        // the loaded entries below the scanned path that weren't found don't exist anymore
+        let paths = &mut self.paths;
+        self.unvalidated.retain(|file_id, loaded| {
+            loaded.retain(|p| {
+                let is_scanned = matches!(
+                    p.strip_prefix(path),
+                    Ok(relative) if relative.components().count() <= depth
+                );
+                if is_scanned && paths.get(p) == Some(file_id) {
+                    paths.remove(p);
+                }
+                !is_scanned
+            });
+            !loaded.is_empty()
+        });
+    }
+
+    fn remove_path(&mut self, path: &Path) {
+        self.paths.retain(|p, _| !p.starts_with(path));
+        self.unvalidated.retain(|_, loaded| {
+            loaded.retain(|p| !p.starts_with(path));
+            !loaded.is_empty()
+        });
+    }
+
+    fn rename_path(&mut self, from: &Path, to: &Path) {
+        if rename_paths(&mut self.paths, from, to) {
+            self.unvalidated.retain(|_, loaded| {
+                loaded.retain(|p| !p.starts_with(to));
+                !loaded.is_empty()
+            });
+            for p in self.unvalidated.values_mut().flatten() {
+                if let Ok(relative) = p.strip_prefix(from) {
+                    *p = if relative.as_os_str().is_empty() {
+                        to.to_path_buf()
+                    } else {
+                        to.join(relative)
+                    };
+                }
+            }
+        } else {
+            self.add_path(to);
+        }
+    }
+
+    fn rescan(&mut self) {
+        for (root, _) in self.roots.clone() {
+            self.add_path(&root);
+        }
+    }
+
+    fn add_root(&mut self, path: &Path, recursive_mode: RecursiveMode) {
+        PersistentFileIdMap::add_root(self, path, recursive_mode);
+    }
+
+    fn remove_root(&mut self, path: &Path) {
+        PersistentFileIdMap::remove_root(self, path);
+    }
+}
+
+/// A cache holding the file IDs of a [`MemoryFs`].
+///
+/// Works like [`FileIdMap`], but reads the inode numbers of the in-memory file system instead of
+/// the disk. Together with a [`MemoryWatcher`](notify::MemoryWatcher), this allows testing the
+/// debouncer without touching the disk.
+#[cfg(any(test, feature = "memory"))]
+#[derive(Debug, Clone)]
+pub struct MemoryFileIdMap {
+    fs: MemoryFs,
+    paths: HashMap<PathBuf, FileId>,
+    roots: Vec<(PathBuf, RecursiveMode)>,
+}
+
+#[cfg(any(test, feature = "memory"))]
+impl MemoryFileIdMap {
+    /// Construct an empty cache for `fs`.
+    pub fn new(fs: &MemoryFs) -> Self {
+        Self {
+            fs: fs.clone(),
+            paths: HashMap::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Add a path to the cache, see [`FileIdMap::add_root`].
+    pub fn add_root(&mut self, path: impl Into<PathBuf>, recursive_mode: RecursiveMode) {
+        let path = path.into();
+
+        self.roots.push((path.clone(), recursive_mode));
+
+        self.add_path(&path);
+    }
+
+    /// Remove a path form the cache, see [`FileIdMap::remove_root`].
+    pub fn remove_root(&mut self, path: impl AsRef<Path>) {
+        self.roots.retain(|(root, _)| !root.starts_with(&path));
+
+        self.remove_path(path.as_ref());
+    }
+
+    fn add_path_with_depth(&mut self, path: &Path, depth: usize) {
+        let ino = match self.fs.inode(path) {
+            Some(ino) => ino,
+            None => return,
+        };
+        self.paths
+            .insert(path.to_path_buf(), FileId::new_inode(0, ino));
+
+        if depth > 0 {
+            for child in self.fs.read_dir(path).unwrap_or_default() {
+                self.add_path_with_depth(&child, depth - 1);
+            }
+        }
+    }
+}
+
+#[cfg(any(test, feature = "memory"))]
+impl FileIdCache for MemoryFileIdMap {
+    fn cached_file_id(&self, path: &Path) -> Option<&FileId> {
+        self.paths.get(path)
+    }
+
+    fn add_path(&mut self, path: &Path) {
+        let is_recursive = matches!(
+            innermost_root(&self.roots, path),
+            Some(RecursiveMode::Recursive)
+        );
+
+        self.add_path_with_depth(path, FileIdMap::dir_scan_depth(is_recursive));
+    }
+
+    fn remove_path(&mut self, path: &Path) {
+        self.paths.retain(|p, _| !p.starts_with(path));
+    }
+
+    fn rename_path(&mut self, from: &Path, to: &Path) {
+        if !rename_paths(&mut self.paths, from, to) {
+            self.add_path(to);
+        }
+    }
+
+    fn rescan(&mut self) {
+        for (root, _) in self.roots.clone() {
+            self.add_path(&root);
+        }
+    }
+
+    fn add_root(&mut self, path: &Path, recursive_mode: RecursiveMode) {
+        MemoryFileIdMap::add_root(self, path, recursive_mode);
+    }
+
+    fn remove_root(&mut self, path: &Path) {
+        MemoryFileIdMap::remove_root(self, path);
+    }
+}
+
+/// Returns the setting of the innermost root containing `path`, roots may be nested.
+fn innermost_root<'a, T>(roots: &'a [(PathBuf, T)], path: &Path) -> Option<&'a T> {
+    roots
+        .iter()
+        .filter(|(root, _)| path.starts_with(root))
+        .max_by_key(|(root, _)| root.components().count())
+        .map(|(_, setting)| setting)
+}
+
+/// Read the file IDs of `path` and its children up to `depth`.
+fn scan_file_ids(path: &Path, depth: usize) -> impl Iterator<Item = (PathBuf, FileId)> {
+    WalkDir::new(path)
+        .follow_links(true)
+        .max_depth(depth)
+        .into_iter()
+        .filter_map(|entry| {
+            let path = entry.ok()?.into_path();
+            let file_id = get_file_id(&path).ok()?;
+            Some((path, file_id))
+        })
+}
+
+/// Move the cached file IDs of `from` and its children to `to`, replacing the IDs of `to`.
+///
+/// Returns `false` if `from` wasn't cached.
+fn rename_paths(paths: &mut HashMap<PathBuf, FileId>, from: &Path, to: &Path) -> bool {
+    if !paths.contains_key(from) {
+        return false;
+    }
+
+    let moved: Vec<_> = paths
+        .keys()
+        .filter(|path| path.starts_with(from))
+        .cloned()
+        .collect();
+
+    paths.retain(|path, _| !path.starts_with(to));
+
+    for path in moved {
+        if let Some(file_id) = paths.remove(&path) {
+            // `strip_prefix` can't fail, `path` starts with `from`
+            let relative = path.strip_prefix(from).unwrap_or(&path);
+            let target = if relative.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(relative)
+            };
+            paths.insert(target, file_id);
+        }
+    }
+
+    true
 }
 
 /// An implementation of the `FileIdCache` trait that doesn't hold any data.
 ///
 /// This pseudo cache can be used to disable the file tracking using file system IDs.
+#[derive(Debug, Clone, Copy, Default)]
 pub struct NoCache;
 
 impl FileIdCache for NoCache {
@@ -137,4 +532,8 @@ impl FileIdCache for NoCache {
     fn remove_path(&mut self, _path: &Path) {}
 
     fn rescan(&mut self) {}
+
+    fn add_root(&mut self, _path: &Path, _recursive_mode: RecursiveMode) {}
+
+    fn remove_root(&mut self, _path: &Path) {}
 }
@@ -0,0 +1,78 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+
+#[cfg(test)]
+use mock_instant::Instant;
+
+#[cfg(not(test))]
+use std::time::Instant;
+
+/// The source of the current time used by the debouncer.
+///
+/// The time is read when an event is added and when the debouncer checks for expired events.
+pub trait Clock: Send + 'static {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+}
+
+impl fmt::Debug for dyn Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Clock").field("now", &self.now()).finish()
+    }
+}
+
+/// A clock using the system time, this is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves forward when told to.
+///
+/// Clones share the same time, so a clone can be kept to control a debouncer created with
+/// [`new_debouncer_manual`](crate::new_debouncer_manual).
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl ManualClock {
+    /// Create a clock starting at the current system time.
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// Create a clock starting at `now`.
+    pub fn starting_at(now: Instant) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Move the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock() += duration;
+    }
+
+    /// Set the current time.
+    pub fn set(&self, now: Instant) {
+        *self.now.lock() = now;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock()
+    }
+}
@@ -0,0 +1,346 @@
+use std::{error::Error as StdError, fmt, io, sync::Arc, time::Duration};
+
+use notify::Error;
+
+use crate::{DebouncePolicy, TimeoutResolver};
+
+/// Configuration for the debouncer
+///
+/// This mirrors the configuration of `notify-debouncer-mini`, so switching between the two
+/// debouncers only requires adapting the settings specific to one of them.
+///
+/// ```rust
+/// # use std::time::Duration;
+/// # use notify_debouncer_full::Config;
+/// let config = Config::default()
+///     .with_timeout(Duration::from_secs(1))
+///     .with_tick_rate(Some(Duration::from_millis(100)));
+/// assert!(config.validate().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Config {
+    timeout: Duration,
+    tick_rate: Option<Duration>,
+    policy: DebouncePolicy,
+    timeout_resolver: Option<Arc<dyn TimeoutResolver>>,
+    close_write_grace_period: Option<Duration>,
+    collapse_directories: bool,
+    notify_config: notify::Config,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(500),
+            tick_rate: None,
+            policy: DebouncePolicy::default(),
+            timeout_resolver: None,
+            close_write_grace_period: None,
+            collapse_directories: false,
+            notify_config: notify::Config::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Set timeout
+    ///
+    /// Timeout is the amount of time after which a debounced event is emitted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// Set the tick rate
+    ///
+    /// The tick rate is the interval at which the debouncer checks for expired events. If `None`,
+    /// a tick rate of 1/4 of the timeout is used, which is the default. Debouncers created with
+    /// [`new_debouncer_manual`](crate::new_debouncer_manual) don't tick on their own, so they
+    /// ignore it.
+    pub fn with_tick_rate(mut self, tick_rate: Option<Duration>) -> Self {
+        self.tick_rate = tick_rate;
+        self
+    }
+    /// Set when the events of a path that keeps changing are emitted
+    ///
+    /// See [`DebouncePolicy`], defaults to [`DebouncePolicy::TrailingEdge`].
+    pub fn with_policy(mut self, policy: DebouncePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+    /// Use a different timeout for some paths
+    ///
+    /// See [`Debouncer::set_timeout_resolver`](crate::Debouncer::set_timeout_resolver), `None` by
+    /// default.
+    pub fn with_timeout_resolver(mut self, resolver: Option<Box<dyn TimeoutResolver>>) -> Self {
+        self.timeout_resolver = resolver.map(Arc::from);
+        self
+    }
+    /// Set the grace period after a file was closed after writing
+    ///
+    /// See [`Debouncer::set_close_write_grace_period`](crate::Debouncer::set_close_write_grace_period),
+    /// disabled by default.
+    pub fn with_close_write_grace_period(mut self, grace_period: Option<Duration>) -> Self {
+        self.close_write_grace_period = grace_period;
+        self
+    }
+    /// Collapse the events below a created or removed directory
+    ///
+    /// See [`Debouncer::set_collapse_directories`](crate::Debouncer::set_collapse_directories),
+    /// disabled by default.
+    pub fn with_collapse_directories(mut self, collapse_directories: bool) -> Self {
+        self.collapse_directories = collapse_directories;
+        self
+    }
+    /// Set [`notify::Config`] for the backend
+    pub fn with_notify_config(mut self, notify_config: notify::Config) -> Self {
+        self.notify_config = notify_config;
+        self
+    }
+
+    /// Returns the timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the tick rate, either the configured one or 1/4 of the timeout
+    pub fn tick_rate(&self) -> Duration {
+        self.tick_rate.unwrap_or(self.timeout / 4)
+    }
+
+    /// Returns the policy
+    pub fn policy(&self) -> DebouncePolicy {
+        self.policy
+    }
+
+    /// Returns the timeout resolver
+    pub fn timeout_resolver(&self) -> Option<&dyn TimeoutResolver> {
+        self.timeout_resolver.as_deref()
+    }
+
+    pub(crate) fn shared_timeout_resolver(&self) -> Option<Arc<dyn TimeoutResolver>> {
+        self.timeout_resolver.clone()
+    }
+
+    /// Returns the grace period after a file was closed after writing
+    pub fn close_write_grace_period(&self) -> Option<Duration> {
+        self.close_write_grace_period
+    }
+
+    /// Returns whether the events below created or removed directories are collapsed
+    pub fn collapse_directories(&self) -> bool {
+        self.collapse_directories
+    }
+
+    /// Returns the [`notify::Config`] for the backend
+    pub fn notify_config(&self) -> notify::Config {
+        self.notify_config
+    }
+
+    /// Check the configuration for contradicting settings
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.validate_policy()?;
+
+        let tick_rate = self.tick_rate();
+        if tick_rate.is_zero() {
+            return Err(ConfigError::ZeroTickRate);
+        }
+        if tick_rate > self.timeout {
+            return Err(ConfigError::TickRateExceedsTimeout {
+                tick_rate,
+                timeout: self.timeout,
+            });
+        }
+        match self.policy {
+            DebouncePolicy::MaxWait(max_wait) if max_wait < tick_rate => {
+                Err(ConfigError::MaxWaitBelowTickRate {
+                    max_wait,
+                    tick_rate,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the settings that don't depend on the tick rate
+    pub(crate) fn validate_policy(&self) -> Result<(), ConfigError> {
+        match self.policy {
+            DebouncePolicy::MaxWait(max_wait) if max_wait.is_zero() => {
+                Err(ConfigError::ZeroMaxWait)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An invalid debouncer [`Config`]
+///
+/// When creating a debouncer, the error is returned as [`NewDebouncerError::Config`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The tick rate is zero, which would keep the debouncer thread busy
+    ///
+    /// This is also the case for a zero timeout without an explicit tick rate.
+    ZeroTickRate,
+    /// The tick rate is longer than the timeout, so events would be emitted late
+    TickRateExceedsTimeout {
+        /// The configured tick rate
+        tick_rate: Duration,
+        /// The configured timeout
+        timeout: Duration,
+    },
+    /// The [`DebouncePolicy::MaxWait`] duration is zero, so events wouldn't be debounced at all
+    ZeroMaxWait,
+    /// The [`DebouncePolicy::MaxWait`] duration is shorter than the tick rate, so events would be
+    /// emitted late
+    MaxWaitBelowTickRate {
+        /// The configured max wait
+        max_wait: Duration,
+        /// The configured or derived tick rate
+        tick_rate: Duration,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ZeroTickRate => write!(f, "Invalid tick_rate, tick rate is zero"),
+            Self::TickRateExceedsTimeout { tick_rate, timeout } => write!(
+                f,
+                "Invalid tick_rate, tick rate {tick_rate:?} > {timeout:?} timeout"
+            ),
+            Self::ZeroMaxWait => write!(f, "Invalid policy, max wait is zero"),
+            Self::MaxWaitBelowTickRate {
+                max_wait,
+                tick_rate,
+            } => write!(
+                f,
+                "Invalid policy, max wait {max_wait:?} < {tick_rate:?} tick rate"
+            ),
+        }
+    }
+}
+
+impl StdError for ConfigError {}
+
+impl From<ConfigError> for Error {
+    fn from(err: ConfigError) -> Self {
+        Error::io(io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
+}
+
+/// An error creating a debouncer from a [`Config`]
+///
+/// Converts into a [`notify::Error`], so `?` still works in functions returning
+/// [`notify::Result`]. An invalid configuration then becomes an
+/// [`ErrorKind::Io`](notify::ErrorKind::Io) error of kind
+/// [`InvalidInput`](io::ErrorKind::InvalidInput).
+///
+/// ```rust
+/// # use std::time::Duration;
+/// use notify_debouncer_full::{
+///     new_debouncer_with_config, notify::NullWatcher, Config, ConfigError, DebounceEventResult,
+///     NewDebouncerError, NoCache,
+/// };
+///
+/// let config = Config::default().with_tick_rate(Some(Duration::ZERO));
+/// let error = new_debouncer_with_config::<_, NullWatcher, _>(
+///     config,
+///     |_: DebounceEventResult| {},
+///     NoCache,
+/// )
+/// .unwrap_err();
+///
+/// assert!(matches!(error, NewDebouncerError::Config(ConfigError::ZeroTickRate)));
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NewDebouncerError {
+    /// The configuration is invalid
+    Config(ConfigError),
+    /// The watcher or the debouncer thread couldn't be started
+    Notify(Error),
+}
+
+impl fmt::Display for NewDebouncerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(err) => err.fmt(f),
+            Self::Notify(err) => err.fmt(f),
+        }
+    }
+}
+
+impl StdError for NewDebouncerError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::Config(err) => Some(err),
+            Self::Notify(err) => Some(err),
+        }
+    }
+}
+
+impl From<ConfigError> for NewDebouncerError {
+    fn from(err: ConfigError) -> Self {
+        Self::Config(err)
+    }
+}
+
+impl From<Error> for NewDebouncerError {
+    fn from(err: Error) -> Self {
+        Self::Notify(err)
+    }
+}
+
+impl From<NewDebouncerError> for Error {
+    fn from(err: NewDebouncerError) -> Self {
+        match err {
+            NewDebouncerError::Config(err) => err.into(),
+            NewDebouncerError::Notify(err) => err,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        let config = Config::default().with_timeout(Duration::from_secs(1));
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.tick_rate(), Duration::from_millis(250));
+
+        let config = config.with_tick_rate(Some(Duration::from_secs(2)));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::TickRateExceedsTimeout {
+                tick_rate: Duration::from_secs(2),
+                timeout: Duration::from_secs(1)
+            })
+        );
+
+        let config = config.with_tick_rate(Some(Duration::ZERO));
+        assert_eq!(config.validate(), Err(ConfigError::ZeroTickRate));
+
+        let config = Config::default().with_timeout(Duration::ZERO);
+        assert_eq!(config.tick_rate(), Duration::ZERO);
+        assert_eq!(config.validate(), Err(ConfigError::ZeroTickRate));
+
+        let config = Config::default().with_policy(DebouncePolicy::MaxWait(Duration::ZERO));
+        assert_eq!(config.validate(), Err(ConfigError::ZeroMaxWait));
+
+        let config = Config::default()
+            .with_timeout(Duration::from_secs(1))
+            .with_policy(DebouncePolicy::MaxWait(Duration::from_millis(100)));
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::MaxWaitBelowTickRate {
+                max_wait: Duration::from_millis(100),
+                tick_rate: Duration::from_millis(250)
+            })
+        );
+        let config = config.with_tick_rate(Some(Duration::from_millis(100)));
+        assert_eq!(config.validate(), Ok(()));
+    }
+}
@@ -9,18 +9,36 @@ use std::time::Instant;
 use notify::Event;
 
 /// A debounced event is emitted after a short delay.
+///
+/// New fields may be added, use [`DebouncedEvent::new`] to construct it.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub struct DebouncedEvent {
     /// The original event.
     pub event: Event,
 
     /// The time at which the event occurred.
     pub time: Instant,
+
+    /// The number of entries created or removed below the directory of this event, whose events
+    /// were collapsed into this one.
+    ///
+    /// For a removed directory, these are the entries with pending events, entries that were
+    /// created within the timeout aren't counted.
+    ///
+    /// This is always zero, unless directory collapsing is enabled, see
+    /// [`Debouncer::set_collapse_directories`](crate::Debouncer::set_collapse_directories).
+    pub children: usize,
 }
 
 impl DebouncedEvent {
+    /// Construct a debounced event without collapsed children.
     pub fn new(event: Event, time: Instant) -> Self {
-        Self { event, time }
+        Self {
+            event,
+            time,
+            children: 0,
+        }
     }
 }
 
@@ -43,6 +61,7 @@ impl Default for DebouncedEvent {
         Self {
             event: Default::default(),
             time: Instant::now(),
+            children: 0,
         }
     }
 }
@@ -52,6 +71,7 @@ impl From<Event> for DebouncedEvent {
         Self {
             event,
             time: Instant::now(),
+            children: 0,
         }
     }
 }
@@ -7,6 +7,7 @@
 //! * Emits only one `Remove` event when deleting a directory (inotify)
 //! * Doesn't emit duplicate create events
 //! * Doesn't emit `Modify` events after a `Create` event
+//! * Optionally collapses the events below a created or removed directory into a single event
 //!
 //! # Installation
 //!
@@ -42,12 +43,10 @@
 //!
 //! // Add a path to be watched. All files and directories at that path and
 //! // below will be monitored for changes.
-//! debouncer.watcher().watch(Path::new("."), RecursiveMode::Recursive).unwrap();
-//!
-//! // Add the same path to the file ID cache. The cache uses unique file IDs
+//! // The path is added to the file ID cache as well. The cache uses unique file IDs
 //! // provided by the file system and is used to stich together rename events
 //! // in case the notification back-end doesn't emit rename cookies.
-//! debouncer.cache().add_root(Path::new("."), RecursiveMode::Recursive);
+//! debouncer.watch(Path::new("."), RecursiveMode::Recursive).unwrap();
 //! ```
 //!
 //! # Features
@@ -56,21 +55,27 @@
 //!
 //! - `crossbeam` enabled by default, adds [`DebounceEventHandler`](DebounceEventHandler) support for crossbeam channels.
 //!   Also enables crossbeam-channel in the re-exported notify. You may want to disable this when using the tokio async runtime.
-//! - `serde` enables serde support for events.
+//! - `serde` enables serde support for events and adds the `PersistentFileIdMap` file ID cache.
+//! - `memory` adds the `MemoryFileIdMap` file ID cache for the in-memory file system of notify,
+//!   for deterministic tests. Also enables the `memory` feature of the re-exported notify.
 //!
 //! # Caveats
 //!
 //! As all file events are sourced from notify, the [known problems](https://docs.rs/notify/latest/notify/#known-problems) section applies here too.
 
 mod cache;
+mod clock;
+mod config;
 mod debounced_event;
+mod timeout;
 
 #[cfg(test)]
 mod testing;
 
 use std::{
-    collections::{HashMap, VecDeque},
-    path::PathBuf,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -78,16 +83,24 @@ use std::{
     time::Duration,
 };
 
+#[cfg(any(test, feature = "memory"))]
+pub use cache::MemoryFileIdMap;
+#[cfg(feature = "serde")]
+pub use cache::PersistentFileIdMap;
 pub use cache::{FileIdCache, FileIdMap, NoCache};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use config::{Config, ConfigError, NewDebouncerError};
 pub use debounced_event::DebouncedEvent;
+pub use timeout::{TimeoutResolver, Timeouts};
 
 pub use file_id;
 pub use notify;
+pub use notify::DebouncedWatcher;
 
 use file_id::FileId;
 use notify::{
-    event::{ModifyKind, RemoveKind, RenameMode},
-    Error, ErrorKind, Event, EventKind, RecommendedWatcher, Watcher,
+    event::{AccessKind, AccessMode, ModifyKind, RemoveKind, RenameMode},
+    Error, ErrorKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
 };
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 
@@ -150,6 +163,44 @@ pub type DebounceEventResult = Result<Vec<DebouncedEvent>, Vec<Error>>;
 
 type DebounceData<T> = Arc<Mutex<DebounceDataInner<T>>>;
 
+/// The events of a path that are waiting for their timeout, see [`Debouncer::pending_events`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PendingPath {
+    /// The path of the events.
+    ///
+    /// For renames, this is the target path.
+    pub path: PathBuf,
+
+    /// The kinds of the events, oldest first.
+    pub kinds: Vec<EventKind>,
+
+    /// The time since the oldest event occurred.
+    pub age: Duration,
+}
+
+/// When the events of a path that keeps changing are emitted.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum DebouncePolicy {
+    /// Emit the events of a path once it didn't change for the timeout.
+    TrailingEdge,
+    /// Emit the first events of a path with the next tick and suppress further events of the same
+    /// kind until the path didn't change for the timeout.
+    ///
+    /// Events of a different kind, e.g. a `Remove` after a `Modify`, are still emitted.
+    LeadingEdge,
+    /// Like `TrailingEdge`, but while a path keeps changing, its events are emitted once the
+    /// oldest one is `max_wait` old.
+    MaxWait(Duration),
+}
+
+impl Default for DebouncePolicy {
+    fn default() -> Self {
+        Self::TrailingEdge
+    }
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
 struct Queue {
     /// Events must be stored in the following order:
@@ -157,6 +208,18 @@ struct Queue {
     /// 2. `rename` event
     /// 3. Other events
     events: VecDeque<DebouncedEvent>,
+
+    /// Emit all events at this time, regardless of the timeout.
+    flush_at: Option<Instant>,
+}
+
+/// The changes of a path for which events were emitted with the leading edge policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Burst {
+    /// Time of the latest event, emitted or not
+    last: Instant,
+    /// Kinds of the emitted events
+    kinds: HashSet<EventKind>,
 }
 
 impl Queue {
@@ -187,10 +250,16 @@ pub(crate) struct DebounceDataInner<T> {
     rescan_event: Option<DebouncedEvent>,
     errors: Vec<Error>,
     timeout: Duration,
+    timeout_resolver: Option<Arc<dyn TimeoutResolver>>,
+    close_write_grace_period: Option<Duration>,
+    collapse_directories: bool,
+    policy: DebouncePolicy,
+    bursts: HashMap<PathBuf, Burst>,
+    clock: Box<dyn Clock>,
 }
 
 impl<T: FileIdCache> DebounceDataInner<T> {
-    pub(crate) fn new(cache: T, timeout: Duration) -> Self {
+    pub(crate) fn new(cache: T, timeout: Duration, clock: Box<dyn Clock>) -> Self {
         Self {
             queues: HashMap::new(),
             cache,
@@ -198,17 +267,66 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             rescan_event: None,
             errors: Vec::new(),
             timeout,
+            timeout_resolver: None,
+            close_write_grace_period: None,
+            collapse_directories: false,
+            policy: DebouncePolicy::default(),
+            bursts: HashMap::new(),
+            clock,
         }
     }
 
+    pub(crate) fn with_config(cache: T, config: &Config, clock: Box<dyn Clock>) -> Self {
+        let mut data = Self::new(cache, config.timeout(), clock);
+        data.set_policy(config.policy());
+        data.timeout_resolver = config.shared_timeout_resolver();
+        data.close_write_grace_period = config.close_write_grace_period();
+        data.collapse_directories = config.collapse_directories();
+        data
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: DebouncePolicy) {
+        self.policy = policy;
+        self.bursts.clear();
+    }
+
     /// Retrieve a vec of debounced events, removing them if not continuous
     pub fn debounced_events(&mut self) -> Vec<DebouncedEvent> {
-        let now = Instant::now();
+        self.take_events(false)
+    }
+
+    /// Retrieve a vec of all pending events, regardless of their timeout
+    pub fn flushed_events(&mut self) -> Vec<DebouncedEvent> {
+        self.take_events(true)
+    }
+
+    /// Retrieve a snapshot of the pending events, grouped by path
+    pub fn pending(&self) -> Vec<PendingPath> {
+        let now = self.clock.now();
+        let mut pending: Vec<_> = self
+            .queues
+            .iter()
+            .map(|(path, queue)| PendingPath {
+                path: path.clone(),
+                kinds: queue.events.iter().map(|event| event.kind).collect(),
+                age: queue
+                    .events
+                    .front()
+                    .map(|event| now.saturating_duration_since(event.time))
+                    .unwrap_or_default(),
+            })
+            .collect();
+        pending.sort_by(|a, b| a.path.cmp(&b.path));
+        pending
+    }
+
+    fn take_events(&mut self, force: bool) -> Vec<DebouncedEvent> {
+        let now = self.clock.now();
         let mut events_expired = Vec::with_capacity(self.queues.len());
         let mut queues_remaining = HashMap::with_capacity(self.queues.len());
 
         if let Some(event) = self.rescan_event.take() {
-            if now.saturating_duration_since(event.time) >= self.timeout {
+            if force || now.saturating_duration_since(event.time) >= self.timeout {
                 log::trace!("debounced event: {event:?}");
                 events_expired.push(event);
             } else {
@@ -216,12 +334,49 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             }
         }
 
+        if self.policy == DebouncePolicy::LeadingEdge {
+            self.bursts = std::mem::take(&mut self.bursts)
+                .into_iter()
+                .filter(|(path, burst)| {
+                    now.saturating_duration_since(burst.last) < self.timeout_for(path)
+                })
+                .collect();
+        }
+
         // TODO: perfect fit for drain_filter https://github.com/rust-lang/rust/issues/59618
-        for (path, mut queue) in self.queues.drain() {
+        for (path, mut queue) in std::mem::take(&mut self.queues) {
             let mut kind_index = HashMap::new();
+            let timeout = self.timeout_for(&path);
+            let flush = force
+                || matches!(queue.flush_at, Some(flush_at) if flush_at <= now)
+                || match self.policy {
+                    DebouncePolicy::TrailingEdge => false,
+                    DebouncePolicy::LeadingEdge => true,
+                    DebouncePolicy::MaxWait(max_wait) => matches!(
+                        queue.events.front(),
+                        Some(event) if now.saturating_duration_since(event.time) >= max_wait
+                    ),
+                };
 
             while let Some(event) = queue.events.pop_front() {
-                if now.saturating_duration_since(event.time) >= self.timeout {
+                let age = now.saturating_duration_since(event.time);
+
+                // keep unmatched rename events long enough to pair them, regardless of the policy
+                let expired = if event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::From))
+                {
+                    force || age >= timeout.max(self.timeout)
+                } else {
+                    flush || age >= timeout
+                };
+
+                if expired {
+                    if self.policy == DebouncePolicy::LeadingEdge
+                        && !self.is_leading_edge(&path, &event)
+                    {
+                        log::trace!("suppressed event: {event:?}");
+                        continue;
+                    }
+
                     // remove previous event of the same kind
                     if let Some(idx) = kind_index.get(&event.kind).copied() {
                         events_expired.remove(idx);
@@ -233,6 +388,10 @@ impl<T: FileIdCache> DebounceDataInner<T> {
                         })
                     }
 
+                    if event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::From)) {
+                        self.drop_unmatched_rename(&event);
+                    }
+
                     kind_index.insert(event.kind, events_expired.len());
 
                     events_expired.push(event);
@@ -262,6 +421,38 @@ impl<T: FileIdCache> DebounceDataInner<T> {
         events_expired
     }
 
+    /// Returns the timeout for the events of `path`, as given by the resolver if there is one
+    fn timeout_for(&self, path: &Path) -> Duration {
+        self.timeout_resolver
+            .as_ref()
+            .and_then(|resolver| resolver.timeout(path))
+            .unwrap_or(self.timeout)
+    }
+
+    /// Returns whether the event has to be emitted with the leading edge policy, i.e. whether it
+    /// starts a new burst of changes for the path or is the first of its kind within the burst.
+    fn is_leading_edge(&mut self, path: &Path, event: &DebouncedEvent) -> bool {
+        let timeout = self.timeout_for(path);
+        match self.bursts.get_mut(path) {
+            Some(burst) if event.time.saturating_duration_since(burst.last) < timeout => {
+                burst.last = burst.last.max(event.time);
+                burst.kinds.insert(event.kind)
+            }
+            _ => {
+                let mut kinds = HashSet::new();
+                kinds.insert(event.kind);
+                self.bursts.insert(
+                    path.to_path_buf(),
+                    Burst {
+                        last: event.time,
+                        kinds,
+                    },
+                );
+                true
+            }
+        }
+    }
+
     /// Returns all currently stored errors
     pub fn errors(&mut self) -> Vec<Error> {
         let mut v = Vec::new();
@@ -280,17 +471,37 @@ impl<T: FileIdCache> DebounceDataInner<T> {
 
         if event.need_rescan() {
             self.cache.rescan();
-            self.rescan_event = Some(event.into());
+            self.rescan_event = Some(DebouncedEvent::new(event, self.clock.now()));
             return;
         }
 
         let path = &event.paths[0];
 
+        if let Some(grace_period) = self.close_write_grace_period {
+            let path = path.clone();
+            let is_close_write =
+                event.kind == EventKind::Access(AccessKind::Close(AccessMode::Write));
+
+            self.add_event_to_queue(event);
+
+            // any other event means the file is still in use
+            let now = self.clock.now();
+            if let Some(queue) = self.queues.get_mut(&path) {
+                queue.flush_at = is_close_write.then(|| now + grace_period);
+            }
+        } else {
+            self.add_event_to_queue(event);
+        }
+    }
+
+    fn add_event_to_queue(&mut self, event: Event) {
+        let path = &event.paths[0];
+
         match &event.kind {
             EventKind::Create(_) => {
                 self.cache.add_path(path);
 
-                self.push_event(event, Instant::now());
+                self.push_event(event, self.clock.now());
             }
             EventKind::Modify(ModifyKind::Name(rename_mode)) => {
                 match rename_mode {
@@ -316,7 +527,7 @@ impl<T: FileIdCache> DebounceDataInner<T> {
                 }
             }
             EventKind::Remove(_) => {
-                self.push_remove_event(event, Instant::now());
+                self.push_remove_event(event, self.clock.now());
             }
             EventKind::Other => {
                 // ignore meta events
@@ -326,27 +537,41 @@ impl<T: FileIdCache> DebounceDataInner<T> {
                     self.cache.add_path(path);
                 }
 
-                self.push_event(event, Instant::now());
+                self.push_event(event, self.clock.now());
             }
         }
     }
 
     fn handle_rename_from(&mut self, event: Event) {
-        let time = Instant::now();
+        let time = self.clock.now();
         let path = &event.paths[0];
 
-        // store event
+        // the previous rename wasn't matched, so it was a move out
+        if let Some((previous_event, _)) = &self.rename_event {
+            let previous_path = &previous_event.paths[0];
+            if !path.starts_with(previous_path) && !previous_path.starts_with(path) {
+                self.cache.remove_path(previous_path);
+            }
+        }
+
+        // store event, the cached file ids are kept until the rename is matched, so the subtree of
+        // a renamed directory can be moved
         let file_id = self.cache.cached_file_id(path).cloned();
         self.rename_event = Some((DebouncedEvent::new(event.clone(), time), file_id));
 
-        self.cache.remove_path(path);
-
         self.push_event(event, time);
     }
 
-    fn handle_rename_to(&mut self, event: Event) {
-        self.cache.add_path(&event.paths[0]);
+    /// The rename `From` event is emitted without a matching `To` event, so it was a move out and
+    /// the cached file ids of the source aren't needed anymore.
+    fn drop_unmatched_rename(&mut self, event: &DebouncedEvent) {
+        if matches!(&self.rename_event, Some((rename_event, _)) if rename_event == event) {
+            self.rename_event = None;
+            self.cache.remove_path(&event.paths[0]);
+        }
+    }
 
+    fn handle_rename_to(&mut self, event: Event) {
         let trackers_match = self
             .rename_event
             .as_ref()
@@ -359,6 +584,11 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             })
             .unwrap_or_default();
 
+        if !trackers_match {
+            // the file id of the target is needed to match the rename
+            self.cache.add_path(&event.paths[0]);
+        }
+
         let file_ids_match = self
             .rename_event
             .as_ref()
@@ -377,15 +607,23 @@ impl<T: FileIdCache> DebounceDataInner<T> {
             let time = rename_event.time;
             self.push_rename_event(path, event, time);
         } else {
-            // move in
-            self.push_event(event, Instant::now());
+            // the source was moved out, the target was moved in
+            if let Some((rename_event, _)) = self.rename_event.take() {
+                let source = &rename_event.paths[0];
+                self.cache.remove_path(source);
+                if event.paths[0].starts_with(source) {
+                    self.cache.add_path(&event.paths[0]);
+                }
+            }
+
+            self.push_event(event, self.clock.now());
         }
 
         self.rename_event = None;
     }
 
     fn push_rename_event(&mut self, path: PathBuf, event: Event, time: Instant) {
-        self.cache.remove_path(&path);
+        self.cache.rename_path(&path, &event.paths[0]);
 
         let mut source_queue = self.queues.remove(&path).unwrap_or_default();
 
@@ -421,6 +659,7 @@ impl<T: FileIdCache> DebounceDataInner<T> {
                 event.paths[0].clone(),
                 Queue {
                     events: [event].into(),
+                    flush_at: None,
                 },
             );
         }
@@ -432,26 +671,26 @@ impl<T: FileIdCache> DebounceDataInner<T> {
 
         // insert rename event at the front, unless the file was just created
         if !source_queue.was_created() {
-            source_queue.events.push_front(DebouncedEvent {
-                event: Event {
+            source_queue.events.push_front(DebouncedEvent::new(
+                Event {
                     kind: EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
                     paths: vec![original_path, event.paths[0].clone()],
                     attrs: event.attrs,
                 },
-                time: original_time,
-            });
+                original_time,
+            ));
         }
 
         if let Some(target_queue) = self.queues.get_mut(&event.paths[0]) {
             if !target_queue.was_created() {
-                let mut remove_event = DebouncedEvent {
-                    event: Event {
+                let mut remove_event = DebouncedEvent::new(
+                    Event {
                         kind: EventKind::Remove(RemoveKind::Any),
                         paths: vec![event.paths[0].clone()],
                         attrs: Default::default(),
                     },
-                    time: original_time,
-                };
+                    original_time,
+                );
                 if !target_queue.was_removed() {
                     remove_event.event = remove_event.event.set_info("override");
                 }
@@ -466,28 +705,79 @@ impl<T: FileIdCache> DebounceDataInner<T> {
     fn push_remove_event(&mut self, event: Event, time: Instant) {
         let path = &event.paths[0];
 
-        // remove child queues
-        self.queues.retain(|p, _| !p.starts_with(path) || p == path);
+        // remove child queues, the children created within the timeout never existed for the
+        // event handler and aren't counted
+        let mut children = 0;
+        self.queues.retain(|p, queue| {
+            let is_child = p.starts_with(path) && p != path;
+            if is_child && self.collapse_directories && !queue.was_created() {
+                children += 1;
+            }
+            !is_child
+        });
 
         // remove cached file ids
         self.cache.remove_path(path);
 
+        // the source of a pending rename is gone, so a later `To` event can't belong to it
+        if let Some((rename_event, _)) = &self.rename_event {
+            if rename_event.paths[0].starts_with(path) {
+                self.rename_event = None;
+            }
+        }
+
         match self.queues.get_mut(path) {
             Some(queue) if queue.was_created() => {
                 self.queues.remove(path);
             }
             Some(queue) => {
-                queue.events = [DebouncedEvent::new(event, time)].into();
+                let mut event = DebouncedEvent::new(event, time);
+                event.children = children;
+                queue.events = [event].into();
             }
             None => {
+                let path = path.clone();
                 self.push_event(event, time);
+                if let Some(event) = self
+                    .queues
+                    .get_mut(&path)
+                    .and_then(|queue| queue.events.back_mut())
+                {
+                    event.children = children;
+                }
             }
         }
     }
 
+    /// Returns the queue of the closest parent directory that was created within the timeout,
+    /// if any.
+    fn created_parent_queue(&mut self, path: &Path) -> Option<&mut Queue> {
+        let parent = path.ancestors().skip(1).find(
+            |parent| matches!(self.queues.get(*parent), Some(queue) if queue.was_created()),
+        )?;
+        self.queues.get_mut(parent)
+    }
+
     fn push_event(&mut self, event: Event, time: Instant) {
         let path = &event.paths[0];
 
+        if self.collapse_directories
+            && !matches!(event.kind, EventKind::Modify(ModifyKind::Name(_)))
+        {
+            if let Some(queue) = self.created_parent_queue(path) {
+                if let Some(parent_event) = queue.events.front_mut() {
+                    if event.kind.is_create() {
+                        parent_event.children += 1;
+                    } else if event.kind.is_remove() {
+                        // the child was created within the timeout as well
+                        parent_event.children = parent_event.children.saturating_sub(1);
+                    }
+                }
+                log::trace!("collapsed event: {event:?}");
+                return;
+            }
+        }
+
         if let Some(queue) = self.queues.get_mut(path) {
             // skip duplicate create events and modifications right after creation
             if match event.kind {
@@ -502,6 +792,7 @@ impl<T: FileIdCache> DebounceDataInner<T> {
                 path.to_path_buf(),
                 Queue {
                     events: [DebouncedEvent::new(event, time)].into(),
+                    flush_at: None,
                 },
             );
         }
@@ -509,11 +800,11 @@ impl<T: FileIdCache> DebounceDataInner<T> {
 }
 
 /// Debouncer guard, stops the debouncer on drop.
-#[derive(Debug)]
 pub struct Debouncer<T: Watcher, C: FileIdCache> {
     watcher: T,
     debouncer_thread: Option<std::thread::JoinHandle<()>>,
     data: DebounceData<C>,
+    event_handler: Arc<Mutex<dyn DebounceEventHandler>>,
     stop: Arc<AtomicBool>,
 }
 
@@ -536,6 +827,26 @@ impl<T: Watcher, C: FileIdCache> Debouncer<T, C> {
         self.stop.store(true, Ordering::Relaxed);
     }
 
+    /// Begin watching a path and add it to the file ID cache.
+    ///
+    /// This is the same as calling [`Watcher::watch`] on the [`watcher`](Self::watcher) and
+    /// [`FileIdCache::add_root`] on the [`cache`](Self::cache), which keeps both in sync. The path
+    /// is only added to the cache if it could be watched.
+    pub fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<(), Error> {
+        self.watcher.watch(path, recursive_mode)?;
+        self.data.lock().cache.add_root(path, recursive_mode);
+        Ok(())
+    }
+
+    /// Stop watching a path and remove it from the file ID cache.
+    ///
+    /// The path is only removed from the cache if the watcher stopped watching it.
+    pub fn unwatch(&mut self, path: &Path) -> Result<(), Error> {
+        self.watcher.unwatch(path)?;
+        self.data.lock().cache.remove_root(path);
+        Ok(())
+    }
+
     /// Access to the internally used notify Watcher backend
     pub fn watcher(&mut self) -> &mut T {
         &mut self.watcher
@@ -545,6 +856,100 @@ impl<T: Watcher, C: FileIdCache> Debouncer<T, C> {
     pub fn cache(&mut self) -> MappedMutexGuard<C> {
         MutexGuard::map(self.data.lock(), |data| &mut data.cache)
     }
+
+    /// Emit the events of a path shortly after its file was closed after writing.
+    ///
+    /// An `Access(Close(Write))` event is the strongest signal that a writer is done. With a grace
+    /// period, the events of the path are emitted once it has elapsed, instead of after the full
+    /// timeout. If any other event arrives for the path in the meantime, e.g. because an editor
+    /// reopens the file, the path is debounced as usual. Events are emitted with the next tick,
+    /// so a grace period of zero emits them as soon as possible.
+    ///
+    /// `None` disables this, which is the default.
+    pub fn set_close_write_grace_period(&self, grace_period: Option<Duration>) {
+        self.data.lock().close_write_grace_period = grace_period;
+    }
+
+    /// Collapse the events below a directory that was created or removed within the timeout
+    /// into the event of the directory.
+    ///
+    /// This avoids a flood of events for e.g. a copied or removed checkout. The number of entries
+    /// created or removed below the directory is available as [`DebouncedEvent::children`].
+    /// Renames below a new directory are still emitted.
+    ///
+    /// Child events that occurred before the directory was created aren't collapsed. Disabled by
+    /// default.
+    pub fn set_collapse_directories(&self, collapse_directories: bool) {
+        self.data.lock().collapse_directories = collapse_directories;
+    }
+
+    /// Use a different timeout for some paths, e.g. a short one for stylesheets and a long one
+    /// for source files that trigger a compilation.
+    ///
+    /// Paths for which the resolver returns `None` use the debouncer's timeout. Rename `From`
+    /// events are kept for at least the debouncer's timeout, so they can still be paired with
+    /// their `To` event. Events are ordered chronologically within each batch, but an event with a
+    /// long timeout may be emitted in a later batch than a younger event with a short timeout.
+    ///
+    /// Events are checked with each tick, so the tick rate should suit the shortest timeout.
+    ///
+    /// The initial resolver is set with [`Config::with_timeout_resolver`].
+    pub fn set_timeout_resolver(&self, resolver: Option<Box<dyn TimeoutResolver>>) {
+        self.data.lock().timeout_resolver = resolver.map(Arc::from);
+    }
+
+    /// Set when the events of a path that keeps changing are emitted.
+    ///
+    /// See [`DebouncePolicy`], defaults to [`DebouncePolicy::TrailingEdge`]. Changing the policy
+    /// starts all bursts of changes anew.
+    pub fn set_policy(&self, policy: DebouncePolicy) {
+        self.data.lock().set_policy(policy);
+    }
+
+    /// Emit all events and errors whose timeout has elapsed, on the current thread.
+    ///
+    /// This is required for debouncers created with [`new_debouncer_manual`], which don't have
+    /// an event thread. For other debouncers, it emits expired events without waiting for the
+    /// next tick.
+    pub fn tick(&self) {
+        flush(&self.data, &self.event_handler, false);
+    }
+
+    /// Emit all pending events and errors now, on the current thread, regardless of their timeout.
+    ///
+    /// This is useful before shutting down or before acting on a known set of changes, e.g. after
+    /// saving all files. Rename events whose second half hasn't arrived yet are emitted as they
+    /// are.
+    pub fn flush(&self) {
+        flush(&self.data, &self.event_handler, true);
+    }
+
+    /// Returns a snapshot of the events waiting for their timeout, grouped by path and sorted by
+    /// path.
+    pub fn pending_events(&self) -> Vec<PendingPath> {
+        self.data.lock().pending()
+    }
+}
+
+impl<T: Watcher + fmt::Debug, C: FileIdCache + fmt::Debug> fmt::Debug for Debouncer<T, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debouncer")
+            .field("watcher", &self.watcher)
+            .field("debouncer_thread", &self.debouncer_thread)
+            .field("data", &self.data)
+            .field("stop", &self.stop)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Watcher, C: FileIdCache> DebouncedWatcher for Debouncer<T, C> {
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<(), Error> {
+        Debouncer::watch(self, path, recursive_mode)
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<(), Error> {
+        Debouncer::unwatch(self, path)
+    }
 }
 
 impl<T: Watcher, C: FileIdCache> Drop for Debouncer<T, C> {
@@ -558,36 +963,83 @@ impl<T: Watcher, C: FileIdCache> Drop for Debouncer<T, C> {
 /// Timeout is the amount of time after which a debounced event is emitted.
 ///
 /// If tick_rate is None, notify will select a tick rate that is 1/4 of the provided timeout.
+///
+/// Unlike [`new_debouncer_with_config`], this doesn't use [`Config::validate`]: a tick rate longer
+/// than the timeout is reported as [`ErrorKind::Generic`] and a zero tick rate is accepted.
 pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher, C: FileIdCache + Send + 'static>(
     timeout: Duration,
     tick_rate: Option<Duration>,
-    mut event_handler: F,
+    event_handler: F,
     file_id_cache: C,
     config: notify::Config,
 ) -> Result<Debouncer<T, C>, Error> {
-    let data = Arc::new(Mutex::new(DebounceDataInner::new(file_id_cache, timeout)));
-    let stop = Arc::new(AtomicBool::new(false));
-
-    let tick_div = 4;
-    let tick = match tick_rate {
-        Some(v) => {
-            if v > timeout {
-                return Err(Error::new(ErrorKind::Generic(format!(
-                    "Invalid tick_rate, tick rate {:?} > {:?} timeout!",
-                    v, timeout
-                ))));
-            }
-            v
+    // keeps the checks from before `Config::validate`, which is stricter
+    if let Some(tick_rate) = tick_rate {
+        if tick_rate > timeout {
+            return Err(Error::new(ErrorKind::Generic(format!(
+                "Invalid tick_rate, tick rate {:?} > {:?} timeout!",
+                tick_rate, timeout
+            ))));
         }
-        None => timeout.checked_div(tick_div).ok_or_else(|| {
-            Error::new(ErrorKind::Generic(format!(
-                "Failed to calculate tick as {:?}/{}!",
-                timeout, tick_div
-            )))
-        })?,
-    };
+    }
+
+    start_debouncer(
+        Config::default()
+            .with_timeout(timeout)
+            .with_tick_rate(tick_rate)
+            .with_notify_config(config),
+        event_handler,
+        file_id_cache,
+    )
+}
+
+/// Creates a new debounced watcher from a [`Config`].
+///
+/// An invalid configuration is reported as [`NewDebouncerError::Config`].
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// use notify_debouncer_full::{
+///     new_debouncer_with_config, notify::RecommendedWatcher, Config, DebounceEventResult,
+///     FileIdMap,
+/// };
+///
+/// let config = Config::default()
+///     .with_timeout(Duration::from_secs(2))
+///     .with_collapse_directories(true);
+/// let debouncer = new_debouncer_with_config::<_, RecommendedWatcher, _>(
+///     config,
+///     |result: DebounceEventResult| println!("{result:?}"),
+///     FileIdMap::new(),
+/// )
+/// .unwrap();
+/// ```
+pub fn new_debouncer_with_config<
+    F: DebounceEventHandler,
+    T: Watcher,
+    C: FileIdCache + Send + 'static,
+>(
+    config: Config,
+    event_handler: F,
+    file_id_cache: C,
+) -> Result<Debouncer<T, C>, NewDebouncerError> {
+    config.validate()?;
+    Ok(start_debouncer(config, event_handler, file_id_cache)?)
+}
+
+fn start_debouncer<F: DebounceEventHandler, T: Watcher, C: FileIdCache + Send + 'static>(
+    config: Config,
+    event_handler: F,
+    file_id_cache: C,
+) -> Result<Debouncer<T, C>, Error> {
+    let data = DebounceDataInner::with_config(file_id_cache, &config, Box::new(SystemClock));
+    let data = Arc::new(Mutex::new(data));
+    let event_handler: Arc<Mutex<dyn DebounceEventHandler>> = Arc::new(Mutex::new(event_handler));
+    let stop = Arc::new(AtomicBool::new(false));
 
+    let tick = config.tick_rate();
     let data_c = data.clone();
+    let event_handler_c = event_handler.clone();
     let stop_c = stop.clone();
     let thread = std::thread::Builder::new()
         .name("notify-rs debouncer loop".to_string())
@@ -596,23 +1048,83 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher, C: FileIdCache + S
                 break;
             }
             std::thread::sleep(tick);
-            let send_data;
-            let errors;
-            {
-                let mut lock = data_c.lock();
-                send_data = lock.debounced_events();
-                errors = lock.errors();
-            }
-            if !send_data.is_empty() {
-                event_handler.handle_event(Ok(send_data));
-            }
-            if !errors.is_empty() {
-                event_handler.handle_event(Err(errors));
-            }
+            flush(&data_c, &event_handler_c, false);
         })?;
 
+    let watcher = new_watcher(&data, config.notify_config())?;
+
+    let guard = Debouncer {
+        watcher,
+        debouncer_thread: Some(thread),
+        data,
+        event_handler,
+        stop,
+    };
+
+    Ok(guard)
+}
+
+/// Creates a new debounced watcher without an event thread.
+///
+/// Events are only emitted when [`Debouncer::tick`] is called, and all times are read from
+/// `clock`. Together with a [`ManualClock`], this allows embedding the debouncer in an existing
+/// event loop or testing it deterministically.
+///
+/// The tick rate of the [`Config`] isn't used and thus not validated, the other settings are
+/// applied as in [`new_debouncer_with_config`].
+///
+/// ```rust
+/// # use std::time::Duration;
+/// use notify_debouncer_full::{
+///     new_debouncer_manual, notify::NullWatcher, Config, DebounceEventResult, ManualClock,
+///     NoCache,
+/// };
+///
+/// let clock = ManualClock::new();
+/// let debouncer = new_debouncer_manual::<_, NullWatcher, _, _>(
+///     Config::default().with_timeout(Duration::from_secs(1)),
+///     |result: DebounceEventResult| println!("{result:?}"),
+///     NoCache,
+///     clock.clone(),
+/// )
+/// .unwrap();
+///
+/// clock.advance(Duration::from_secs(1));
+/// debouncer.tick();
+/// ```
+pub fn new_debouncer_manual<
+    F: DebounceEventHandler,
+    T: Watcher,
+    C: FileIdCache + Send + 'static,
+    K: Clock,
+>(
+    config: Config,
+    event_handler: F,
+    file_id_cache: C,
+    clock: K,
+) -> Result<Debouncer<T, C>, NewDebouncerError> {
+    config.validate_policy()?;
+
+    let data = DebounceDataInner::with_config(file_id_cache, &config, Box::new(clock));
+    let data = Arc::new(Mutex::new(data));
+
+    let watcher = new_watcher(&data, config.notify_config())?;
+
+    Ok(Debouncer {
+        watcher,
+        debouncer_thread: None,
+        data,
+        event_handler: Arc::new(Mutex::new(event_handler)),
+        stop: Arc::new(AtomicBool::new(false)),
+    })
+}
+
+fn new_watcher<T: Watcher, C: FileIdCache + Send + 'static>(
+    data: &DebounceData<C>,
+    config: notify::Config,
+) -> Result<T, Error> {
     let data_c = data.clone();
-    let watcher = T::new(
+    T::new(
         move |e: Result<Event, Error>| {
             let mut lock = data_c.lock();
 
@@ -623,16 +1135,33 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher, C: FileIdCache + S
             }
         },
         config,
-    )?;
-
-    let guard = Debouncer {
-        watcher,
-        debouncer_thread: Some(thread),
-        data,
-        stop,
-    };
+    )
+}
 
-    Ok(guard)
+/// Pass expired events and errors to the event handler, or all pending events if `force` is set.
+fn flush<C: FileIdCache>(
+    data: &DebounceData<C>,
+    event_handler: &Mutex<dyn DebounceEventHandler>,
+    force: bool,
+) {
+    let send_data;
+    let errors;
+    {
+        let mut lock = data.lock();
+        send_data = if force {
+            lock.flushed_events()
+        } else {
+            lock.debounced_events()
+        };
+        errors = lock.errors();
+    }
+    let mut event_handler = event_handler.lock();
+    if !send_data.is_empty() {
+        event_handler.handle_event(Ok(send_data));
+    }
+    if !errors.is_empty() {
+        event_handler.handle_event(Err(errors));
+    }
 }
 
 /// Short function to create a new debounced watcher with the recommended debouncer and the built-in file ID cache.
@@ -661,9 +1190,14 @@ mod tests {
     use super::*;
 
     use mock_instant::MockClock;
+    use notify::{
+        event::{AccessKind, AccessMode, CreateKind, DataChange, MetadataKind},
+        memory::MemoryFs,
+        MemoryWatcher, RecursiveMode,
+    };
     use pretty_assertions::assert_eq;
     use rstest::rstest;
-    use testing::TestCase;
+    use testing::{TestCache, TestCase};
 
     #[rstest]
     fn state(
@@ -695,13 +1229,21 @@ mod tests {
             "add_remove_event_after_modify_event",
             "add_remove_event_after_create_and_modify_event",
             "add_remove_parent_event_after_remove_child_event",
+            "add_remove_event_after_rename_from_event",
+            "add_create_and_remove_dir_events_with_collapsed_children",
+            "add_remove_dir_event_with_created_child",
             "add_errors",
             "emit_continuous_modify_content_events",
             "emit_events_in_chronological_order",
             "emit_events_with_a_prepended_rename_event",
+            "emit_events_with_glob_timeouts",
+            "emit_events_with_glob_timeouts_and_leading_edge_policy",
+            "emit_events_on_flush",
             "emit_close_events_only_once",
             "emit_modify_event_after_close_event",
             "emit_needs_rescan_event",
+            "emit_rename_from_event_with_leading_edge_policy",
+            "emit_rename_from_event_with_max_wait_policy",
             "read_file_id_without_create_event"
         )]
         file_name: &str,
@@ -762,10 +1304,18 @@ mod tests {
 
         let backup_time = Instant::now().duration_since(time);
         let backup_queues = state.queues.clone();
+        let backup_bursts = state.bursts.clone();
+        let backup_rename_event = state.rename_event.clone();
+        let backup_rescan_event = state.rescan_event.clone();
+        let backup_cache = state.cache.clone();
 
         for (delay, events) in expected_events {
             MockClock::set_time(backup_time);
             state.queues = backup_queues.clone();
+            state.bursts = backup_bursts.clone();
+            state.rename_event = backup_rename_event.clone();
+            state.rescan_event = backup_rescan_event.clone();
+            state.cache = backup_cache.clone();
 
             match delay.as_str() {
                 "none" => {}
@@ -784,11 +1334,580 @@ mod tests {
                 .map(|event| event.into_debounced_event(time, None))
                 .collect::<Vec<_>>();
 
-            assert_eq!(
-                state.debounced_events(),
-                events,
-                "debounced events after a `{delay}` delay"
-            );
+            if delay == "flush" {
+                assert_eq!(state.flushed_events(), events, "flushed events");
+            } else {
+                assert_eq!(
+                    state.debounced_events(),
+                    events,
+                    "debounced events after a `{delay}` delay"
+                );
+            }
+        }
+    }
+
+    /// A debouncer watching `/watch` of `fs` recursively, driven by `clock`
+    fn memory_debouncer<C: FileIdCache + Send + 'static>(
+        fs: &MemoryFs,
+        clock: &ManualClock,
+        config: Config,
+        cache: C,
+    ) -> (
+        Debouncer<MemoryWatcher, C>,
+        std::sync::mpsc::Receiver<DebounceEventResult>,
+    ) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut debouncer =
+            new_debouncer_manual::<_, MemoryWatcher, _, _>(config, tx, cache, clock.clone())
+                .unwrap();
+        debouncer.watcher().set_fs(fs);
+        debouncer
+            .watcher()
+            .watch(Path::new("/watch"), RecursiveMode::Recursive)
+            .unwrap();
+        (debouncer, rx)
+    }
+
+    #[test]
+    fn memory_fs() {
+        let fs = MemoryFs::new();
+        fs.create_dir_all("/watch/dir").unwrap();
+        fs.write("/watch/dir/existing", b"a").unwrap();
+
+        let mut cache = MemoryFileIdMap::new(&fs);
+        cache.add_root("/watch", RecursiveMode::Recursive);
+
+        let clock = ManualClock::new();
+        let (mut debouncer, rx) = memory_debouncer(
+            &fs,
+            &clock,
+            Config::default().with_timeout(Duration::from_millis(20)),
+            cache,
+        );
+
+        fs.write("/watch/file", b"a").unwrap();
+        clock.advance(Duration::from_millis(1));
+        fs.rename("/watch/file", "/watch/renamed").unwrap();
+        clock.advance(Duration::from_millis(1));
+        fs.remove_dir_all("/watch/dir").unwrap();
+
+        clock.advance(Duration::from_millis(10));
+        debouncer.tick();
+        assert!(rx.try_recv().is_err());
+
+        clock.advance(Duration::from_millis(20));
+        debouncer.tick();
+        let events = rx
+            .try_recv()
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.kind, event.paths.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    EventKind::Create(CreateKind::File),
+                    vec!["/watch/renamed".into()]
+                ),
+                (
+                    EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                    vec!["/watch/renamed".into()]
+                ),
+                (
+                    EventKind::Remove(RemoveKind::Folder),
+                    vec!["/watch/dir".into()]
+                ),
+            ]
+        );
+        assert_eq!(
+            debouncer
+                .cache()
+                .cached_file_id(Path::new("/watch/renamed")),
+            Some(&FileId::new_inode(0, fs.inode("/watch/renamed").unwrap()))
+        );
+        assert_eq!(
+            debouncer
+                .cache()
+                .cached_file_id(Path::new("/watch/dir/existing")),
+            None
+        );
+    }
+
+    #[test]
+    fn close_write_grace_period() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+
+        let clock = ManualClock::new();
+        let (debouncer, rx) = memory_debouncer(
+            &fs,
+            &clock,
+            Config::default().with_timeout(Duration::from_secs(1)),
+            NoCache,
+        );
+        debouncer.set_close_write_grace_period(Some(Duration::from_millis(10)));
+
+        fs.write("/watch/done", b"a").unwrap();
+        fs.write("/watch/reopened", b"a").unwrap();
+        fs.touch("/watch/reopened").unwrap();
+
+        clock.advance(Duration::from_millis(5));
+        debouncer.tick();
+        assert!(rx.try_recv().is_err());
+
+        clock.advance(Duration::from_millis(5));
+        debouncer.tick();
+        let paths = |rx: &std::sync::mpsc::Receiver<DebounceEventResult>| {
+            rx.try_recv()
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .flat_map(|event| event.event.paths)
+                .collect::<std::collections::HashSet<_>>()
+        };
+        assert_eq!(paths(&rx), [PathBuf::from("/watch/done")].into());
+
+        clock.advance(Duration::from_secs(1));
+        debouncer.tick();
+        assert_eq!(paths(&rx), [PathBuf::from("/watch/reopened")].into());
+    }
+
+    fn kinds(rx: &std::sync::mpsc::Receiver<DebounceEventResult>) -> Vec<EventKind> {
+        rx.try_iter()
+            .flat_map(|result| result.unwrap())
+            .map(|event| event.kind)
+            .collect()
+    }
+
+    #[test]
+    fn leading_edge_policy() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+        fs.create_file("/watch/file").unwrap();
+
+        let clock = ManualClock::new();
+        let (debouncer, rx) = memory_debouncer(
+            &fs,
+            &clock,
+            Config::default().with_timeout(Duration::from_secs(1)),
+            NoCache,
+        );
+        debouncer.set_policy(DebouncePolicy::LeadingEdge);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let close_write = EventKind::Access(AccessKind::Close(AccessMode::Write));
+
+        fs.write("/watch/file", b"a").unwrap();
+        debouncer.tick();
+        assert_eq!(kinds(&rx), vec![modify, close_write]);
+
+        // the path keeps changing
+        for _ in 0..20 {
+            clock.advance(Duration::from_millis(100));
+            fs.write("/watch/file", b"a").unwrap();
+            debouncer.tick();
+            assert_eq!(kinds(&rx), vec![]);
+        }
+
+        // other kinds are still emitted
+        fs.remove_file("/watch/file").unwrap();
+        debouncer.tick();
+        assert_eq!(kinds(&rx), vec![EventKind::Remove(RemoveKind::File)]);
+
+        // a new burst starts after the path was quiet for the timeout
+        clock.advance(Duration::from_secs(1));
+        fs.write("/watch/file", b"a").unwrap();
+        debouncer.tick();
+        assert_eq!(
+            kinds(&rx),
+            vec![EventKind::Create(CreateKind::File), close_write]
+        );
+    }
+
+    #[test]
+    fn max_wait_policy() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+        fs.create_file("/watch/file").unwrap();
+
+        let clock = ManualClock::new();
+        let (debouncer, rx) = memory_debouncer(
+            &fs,
+            &clock,
+            Config::default().with_timeout(Duration::from_secs(1)),
+            NoCache,
+        );
+        debouncer.set_policy(DebouncePolicy::MaxWait(Duration::from_millis(300)));
+
+        let mut emitted = Vec::new();
+        for step in 0..10 {
+            fs.write("/watch/file", b"a").unwrap();
+            clock.advance(Duration::from_millis(100));
+            debouncer.tick();
+            if !kinds(&rx).is_empty() {
+                emitted.push(step);
+            }
         }
+        assert_eq!(emitted, vec![2, 5, 8]);
+
+        // the remaining events are emitted after the timeout
+        clock.advance(Duration::from_secs(1));
+        debouncer.tick();
+        assert!(!kinds(&rx).is_empty());
+    }
+
+    #[test]
+    fn manual_debouncer_applies_config() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+
+        let clock = ManualClock::new();
+        let timeouts = Timeouts::new()
+            .with_glob("*.css", Duration::from_millis(50))
+            .unwrap();
+        let config = Config::default()
+            .with_timeout(Duration::from_secs(1))
+            .with_timeout_resolver(Some(Box::new(timeouts)));
+        let (debouncer, rx) = memory_debouncer(&fs, &clock, config, NoCache);
+
+        fs.write("/watch/style.css", b"a").unwrap();
+        fs.write("/watch/main.rs", b"a").unwrap();
+        clock.advance(Duration::from_millis(50));
+        debouncer.tick();
+        let paths: HashSet<_> = rx
+            .try_iter()
+            .flat_map(|result| result.unwrap())
+            .flat_map(|event| event.event.paths)
+            .collect();
+        assert_eq!(paths, HashSet::from([PathBuf::from("/watch/style.css")]));
+
+        let config = Config::default().with_policy(DebouncePolicy::MaxWait(Duration::ZERO));
+        let result = new_debouncer_manual::<_, MemoryWatcher, _, _>(
+            config,
+            |_: DebounceEventResult| {},
+            NoCache,
+            clock,
+        );
+        assert!(matches!(
+            result,
+            Err(NewDebouncerError::Config(ConfigError::ZeroMaxWait))
+        ));
+    }
+
+    #[test]
+    fn unmatched_rename_drops_cached_file_ids() {
+        MockClock::set_time(Duration::default());
+
+        let file_id = FileId::new_inode(1, 1);
+        let cache = TestCache::new(
+            [(PathBuf::from("/watch/source"), file_id)].into(),
+            HashMap::new(),
+        );
+        let mut state =
+            DebounceDataInner::new(cache, Duration::from_millis(50), Box::new(SystemClock));
+        state.add_event(
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+                .add_path("/watch/source".into())
+                .set_tracker(1),
+        );
+
+        // the rename may still be matched
+        assert!(state.debounced_events().is_empty());
+        assert_eq!(
+            state.cache.paths.get(Path::new("/watch/source")),
+            Some(&file_id)
+        );
+
+        MockClock::advance(Duration::from_millis(50));
+        let kinds: Vec<_> = state
+            .debounced_events()
+            .iter()
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![EventKind::Modify(ModifyKind::Name(RenameMode::From))]
+        );
+        assert_eq!(state.rename_event, None);
+        assert!(state.cache.paths.is_empty());
+    }
+
+    #[test]
+    fn rename_directory_moves_cached_file_ids() {
+        let fs = MemoryFs::new();
+        fs.create_dir_all("/watch/dir/sub").unwrap();
+        fs.create_file("/watch/dir/sub/file").unwrap();
+        let file_id = FileId::new_inode(0, fs.inode("/watch/dir/sub/file").unwrap());
+
+        let mut cache = MemoryFileIdMap::new(&fs);
+        cache.add_root("/watch", RecursiveMode::Recursive);
+
+        let clock = ManualClock::new();
+        let (mut debouncer, rx) = memory_debouncer(
+            &fs,
+            &clock,
+            Config::default().with_timeout(Duration::from_millis(20)),
+            cache,
+        );
+
+        fs.rename("/watch/dir", "/watch/moved").unwrap();
+        clock.advance(Duration::from_millis(1));
+        fs.rename("/watch/moved/sub/file", "/watch/file").unwrap();
+
+        clock.advance(Duration::from_millis(20));
+        debouncer.tick();
+        let events = rx
+            .try_recv()
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|event| (event.kind, event.paths.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                    vec!["/watch/dir".into(), "/watch/moved".into()]
+                ),
+                (
+                    EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                    vec!["/watch/moved/sub/file".into(), "/watch/file".into()]
+                ),
+            ]
+        );
+
+        let cache = debouncer.cache();
+        assert_eq!(
+            cache.cached_file_id(Path::new("/watch/file")),
+            Some(&file_id)
+        );
+        assert!(cache
+            .cached_file_id(Path::new("/watch/moved/sub"))
+            .is_some());
+        assert_eq!(cache.cached_file_id(Path::new("/watch/dir/sub")), None);
+        assert_eq!(
+            cache.cached_file_id(Path::new("/watch/moved/sub/file")),
+            None
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn persistent_file_id_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir(root.join("dir")).unwrap();
+        fs::write(root.join("dir/file"), b"a").unwrap();
+        fs::write(root.join("removed"), b"a").unwrap();
+
+        let mut cache = PersistentFileIdMap::new();
+        cache.add_root(root, RecursiveMode::Recursive);
+        let file_id = *cache.cached_file_id(&root.join("dir/file")).unwrap();
+        let saved = serde_json::to_string(&cache).unwrap();
+
+        // changes while the program isn't running
+        fs::rename(root.join("dir/file"), root.join("renamed")).unwrap();
+        fs::remove_file(root.join("removed")).unwrap();
+
+        let mut cache: PersistentFileIdMap = serde_json::from_str(&saved).unwrap();
+        cache.add_root(root, RecursiveMode::Recursive);
+        assert!(!cache.is_validated(&root.join("dir/file")));
+        assert_eq!(cache.cached_file_id(&root.join("dir/file")), Some(&file_id));
+
+        assert_eq!(
+            cache.validate(root),
+            vec![(root.join("dir/file"), root.join("renamed"))]
+        );
+        assert!(cache.is_validated(&root.join("renamed")));
+        assert_eq!(cache.cached_file_id(&root.join("renamed")), Some(&file_id));
+        assert_eq!(cache.cached_file_id(&root.join("dir/file")), None);
+        assert_eq!(cache.cached_file_id(&root.join("removed")), None);
+        assert_eq!(
+            cache.take_offline_renames(),
+            vec![(root.join("dir/file"), root.join("renamed"))]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn persistent_file_id_map_nested_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("dir/sub/deep")).unwrap();
+
+        let mut cache = PersistentFileIdMap::new();
+        cache.add_root(root, RecursiveMode::NonRecursive);
+        cache.add_root(root.join("dir"), RecursiveMode::Recursive);
+        fs::write(root.join("dir/sub/deep/file"), b"a").unwrap();
+
+        // the innermost root is recursive
+        cache.add_path(&root.join("dir/sub"));
+        assert!(cache
+            .cached_file_id(&root.join("dir/sub/deep/file"))
+            .is_some());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn persistent_file_id_map_hard_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("file"), b"a").unwrap();
+        fs::hard_link(root.join("file"), root.join("link")).unwrap();
+
+        let mut cache = PersistentFileIdMap::new();
+        cache.add_root(root, RecursiveMode::Recursive);
+        let file_id = *cache.cached_file_id(&root.join("file")).unwrap();
+        let saved = serde_json::to_string(&cache).unwrap();
+
+        fs::rename(root.join("file"), root.join("renamed")).unwrap();
+
+        let mut cache: PersistentFileIdMap = serde_json::from_str(&saved).unwrap();
+        assert_eq!(
+            cache.validate(root),
+            vec![(root.join("file"), root.join("renamed"))]
+        );
+        assert!(cache.is_validated(&root.join("link")));
+        assert_eq!(cache.cached_file_id(&root.join("link")), Some(&file_id));
+        assert_eq!(cache.cached_file_id(&root.join("renamed")), Some(&file_id));
+    }
+
+    #[test]
+    fn file_id_maps_use_innermost_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("dir/sub/deep")).unwrap();
+        fs::write(root.join("dir/sub/deep/file"), b"a").unwrap();
+
+        let mut cache = FileIdMap::new();
+        cache.add_root(root, RecursiveMode::NonRecursive);
+        cache.add_root(root.join("dir"), RecursiveMode::Recursive);
+        cache.add_path(&root.join("dir/sub"));
+        assert!(cache
+            .cached_file_id(&root.join("dir/sub/deep/file"))
+            .is_some());
+
+        let memory_fs = MemoryFs::new();
+        memory_fs.create_dir_all("/watch/dir/sub/deep").unwrap();
+        memory_fs.create_file("/watch/dir/sub/deep/file").unwrap();
+
+        let mut cache = MemoryFileIdMap::new(&memory_fs);
+        cache.add_root("/watch", RecursiveMode::NonRecursive);
+        cache.add_root("/watch/dir", RecursiveMode::Recursive);
+        cache.add_path(Path::new("/watch/dir/sub"));
+        assert!(cache
+            .cached_file_id(Path::new("/watch/dir/sub/deep/file"))
+            .is_some());
+    }
+
+    #[test]
+    fn pending_events_and_flush() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+        fs.create_file("/watch/existing").unwrap();
+
+        let clock = ManualClock::new();
+        let (debouncer, rx) = memory_debouncer(
+            &fs,
+            &clock,
+            Config::default().with_timeout(Duration::from_secs(1)),
+            NoCache,
+        );
+
+        fs.create_file("/watch/new").unwrap();
+        clock.advance(Duration::from_millis(100));
+        fs.touch("/watch/existing").unwrap();
+        clock.advance(Duration::from_millis(50));
+
+        assert_eq!(
+            debouncer.pending_events(),
+            vec![
+                PendingPath {
+                    path: "/watch/existing".into(),
+                    kinds: vec![EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))],
+                    age: Duration::from_millis(50),
+                },
+                PendingPath {
+                    path: "/watch/new".into(),
+                    kinds: vec![
+                        EventKind::Create(CreateKind::File),
+                        EventKind::Access(AccessKind::Close(AccessMode::Write))
+                    ],
+                    age: Duration::from_millis(150),
+                },
+            ]
+        );
+
+        debouncer.tick();
+        assert!(rx.try_recv().is_err());
+
+        debouncer.flush();
+        assert_eq!(
+            kinds(&rx),
+            vec![
+                EventKind::Create(CreateKind::File),
+                EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))
+            ]
+        );
+        assert!(debouncer.pending_events().is_empty());
+    }
+
+    #[test]
+    fn new_debouncer_opt_errors() {
+        let result = new_debouncer_opt::<_, notify::NullWatcher, _>(
+            Duration::from_secs(1),
+            Some(Duration::from_secs(2)),
+            |_: DebounceEventResult| {},
+            NoCache,
+            notify::Config::default(),
+        );
+        assert!(matches!(
+            result.map_err(|error| error.kind),
+            Err(ErrorKind::Generic(_))
+        ));
+    }
+
+    #[test]
+    fn watch_keeps_cache_in_sync() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+        fs.create_file("/watch/file").unwrap();
+
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut debouncer = new_debouncer_manual::<_, MemoryWatcher, _, _>(
+            Config::default().with_timeout(Duration::from_secs(1)),
+            tx,
+            MemoryFileIdMap::new(&fs),
+            ManualClock::new(),
+        )
+        .unwrap();
+        debouncer.watcher().set_fs(&fs);
+
+        assert!(debouncer
+            .watch(Path::new("/missing"), RecursiveMode::Recursive)
+            .is_err());
+        debouncer
+            .watch(Path::new("/watch"), RecursiveMode::Recursive)
+            .unwrap();
+        assert_eq!(
+            debouncer.cache().cached_file_id(Path::new("/watch/file")),
+            Some(&FileId::new_inode(0, fs.inode("/watch/file").unwrap()))
+        );
+
+        // the cache is kept if the path isn't watched
+        assert!(debouncer.unwatch(Path::new("/watch/file")).is_err());
+        assert!(debouncer
+            .cache()
+            .cached_file_id(Path::new("/watch/file"))
+            .is_some());
+
+        DebouncedWatcher::unwatch(&mut debouncer, Path::new("/watch")).unwrap();
+        assert_eq!(
+            debouncer.cache().cached_file_id(Path::new("/watch/file")),
+            None
+        );
     }
 }
@@ -1,6 +1,7 @@
 use std::{
     collections::{HashMap, VecDeque},
     path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
@@ -14,7 +15,10 @@ use notify::{
     Error, ErrorKind, Event, EventKind,
 };
 
-use crate::{DebounceDataInner, DebouncedEvent, FileIdCache, Queue};
+use crate::{
+    DebounceDataInner, DebouncePolicy, DebouncedEvent, FileIdCache, Queue, SystemClock,
+    TimeoutResolver, Timeouts,
+};
 
 pub(crate) use schema::TestCase;
 
@@ -60,6 +64,10 @@ mod schema {
         ///
         /// Only used for the rename event.
         pub file_id: Option<u64>,
+
+        /// The number of collapsed children
+        #[serde(default)]
+        pub children: usize,
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
@@ -74,6 +82,28 @@ mod schema {
         /// Only used for the initial state.
         pub timeout: Option<u64>,
 
+        /// Debounce policy: `trailing-edge` (default), `leading-edge` or `max-wait`
+        ///
+        /// Only used for the initial state.
+        pub policy: Option<String>,
+
+        /// Maximum wait for the `max-wait` policy
+        ///
+        /// Only used for the initial state.
+        pub max_wait: Option<u64>,
+
+        /// Timeouts per glob pattern
+        ///
+        /// Only used for the initial state, the patterns shouldn't overlap.
+        #[serde(default)]
+        pub timeouts: HashMap<String, u64>,
+
+        /// Whether events below created or removed directories are collapsed
+        ///
+        /// Only used for the initial state.
+        #[serde(default)]
+        pub collapse_directories: bool,
+
         /// The event queues for each file
         #[serde(default)]
         pub queues: HashMap<String, Queue>,
@@ -213,7 +243,9 @@ impl schema::Event {
             event = event.set_flag(flag);
         }
 
-        DebouncedEvent { event, time: time + Duration::from_millis(self.time) }
+        let mut event = DebouncedEvent::new(event, time + Duration::from_millis(self.time));
+        event.children = self.children;
+        event
     }
 }
 
@@ -229,6 +261,7 @@ impl schema::State {
                         .into_iter()
                         .map(|event| event.into_debounced_event(time, Some(&path)))
                         .collect::<VecDeque<_>>(),
+                    flush_at: None,
                 };
                 (path.into(), queue)
             })
@@ -266,6 +299,29 @@ impl schema::State {
             .rescan_event
             .map(|e| e.into_debounced_event(time, None));
 
+        let policy = match self.policy.as_deref() {
+            None | Some("trailing-edge") => DebouncePolicy::TrailingEdge,
+            Some("leading-edge") => DebouncePolicy::LeadingEdge,
+            Some("max-wait") => DebouncePolicy::MaxWait(Duration::from_millis(
+                self.max_wait.expect("`max-wait` policy without `max_wait`"),
+            )),
+            Some(policy) => panic!("unknown policy `{policy}`"),
+        };
+
+        let timeout_resolver = if self.timeouts.is_empty() {
+            None
+        } else {
+            let timeouts =
+                self.timeouts
+                    .into_iter()
+                    .fold(Timeouts::new(), |timeouts, (glob, timeout)| {
+                        timeouts
+                            .with_glob(&glob, Duration::from_millis(timeout))
+                            .unwrap()
+                    });
+            Some(Arc::new(timeouts) as Arc<dyn TimeoutResolver>)
+        };
+
         DebounceDataInner {
             queues,
             cache,
@@ -273,6 +329,12 @@ impl schema::State {
             rescan_event,
             errors: Vec::new(),
             timeout: Duration::from_millis(self.timeout.unwrap_or(50)),
+            timeout_resolver,
+            close_write_grace_period: None,
+            collapse_directories: self.collapse_directories,
+            policy,
+            bursts: HashMap::new(),
+            clock: Box::new(SystemClock),
         }
     }
 }
@@ -0,0 +1,115 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use glob::Pattern;
+use notify::Error;
+
+/// The interface of a per-path timeout resolver.
+///
+/// The debouncer asks the resolver for the timeout of a path whenever it checks for expired
+/// events, so the answer should be cheap to compute. It's shared between clones of a
+/// [`Config`](crate::Config), so it has to be `Sync`.
+pub trait TimeoutResolver: Send + Sync + 'static {
+    /// Get the timeout for the events of `path`.
+    ///
+    /// If `None` is returned, the debouncer's timeout is used.
+    fn timeout(&self, path: &Path) -> Option<Duration>;
+}
+
+impl<F> TimeoutResolver for F
+where
+    F: Fn(&Path) -> Option<Duration> + Send + Sync + 'static,
+{
+    fn timeout(&self, path: &Path) -> Option<Duration> {
+        (self)(path)
+    }
+}
+
+impl fmt::Debug for dyn TimeoutResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutResolver").finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Path(PathBuf),
+    Glob(Pattern),
+}
+
+impl Rule {
+    fn matches(&self, path: &Path) -> bool {
+        match self {
+            Rule::Path(root) => path.starts_with(root),
+            Rule::Glob(pattern) => pattern.matches_path(path),
+        }
+    }
+}
+
+/// A timeout resolver matching paths against a list of rules.
+///
+/// The first matching rule wins, paths that don't match any rule use the debouncer's timeout.
+///
+/// # Example
+///
+/// ```rust
+/// # use std::{path::Path, time::Duration};
+/// use notify_debouncer_full::{TimeoutResolver, Timeouts};
+///
+/// let timeouts = Timeouts::new()
+///     .with_glob("*.css", Duration::from_millis(50))
+///     .unwrap()
+///     .with_glob("*.rs", Duration::from_secs(2))
+///     .unwrap()
+///     .with_path("/project/assets", Duration::from_millis(200));
+///
+/// assert_eq!(
+///     timeouts.timeout(Path::new("/project/assets/style.css")),
+///     Some(Duration::from_millis(50))
+/// );
+/// assert_eq!(
+///     timeouts.timeout(Path::new("/project/assets/logo.png")),
+///     Some(Duration::from_millis(200))
+/// );
+/// assert_eq!(timeouts.timeout(Path::new("/project/README.md")), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Timeouts {
+    rules: Vec<(Rule, Duration)>,
+}
+
+impl Timeouts {
+    /// Construct a resolver without any rules.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Use `timeout` for `path` and all paths below it, e.g. a watch root.
+    pub fn with_path(mut self, path: impl Into<PathBuf>, timeout: Duration) -> Self {
+        self.rules.push((Rule::Path(path.into()), timeout));
+        self
+    }
+
+    /// Use `timeout` for all paths matching the glob `pattern`.
+    ///
+    /// The pattern is matched against the whole path and `*` matches path separators as well,
+    /// so `*.css` matches all CSS files.
+    pub fn with_glob(mut self, pattern: &str, timeout: Duration) -> Result<Self, Error> {
+        let pattern = Pattern::new(pattern)
+            .map_err(|err| Error::generic(&format!("Invalid glob pattern {pattern:?}: {err}")))?;
+        self.rules.push((Rule::Glob(pattern), timeout));
+        Ok(self)
+    }
+}
+
+impl TimeoutResolver for Timeouts {
+    fn timeout(&self, path: &Path) -> Option<Duration> {
+        self.rules
+            .iter()
+            .find(|(rule, _)| rule.matches(path))
+            .map(|(_, timeout)| *timeout)
+    }
+}
@@ -28,7 +28,7 @@
 //!   let mut debouncer = new_debouncer(Duration::from_secs(2), |res: DebounceEventResult| {
 //!       match res {
 //!           Ok(events) => events.iter().for_each(|e|println!("Event {:?} for {:?}",e.kind,e.path)),
-//!           Err(e) => println!("Error {:?}",e),
+//!           Err(errors) => errors.iter().for_each(|e|println!("Error {:?}",e)),
 //!       }
 //!   }).unwrap();
 //!
@@ -55,14 +55,24 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    path::PathBuf,
-    sync::mpsc::{RecvTimeoutError, Sender},
+    collections::{HashMap, HashSet},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 
 pub use notify;
-use notify::{Error, Event, RecommendedWatcher, Watcher};
+pub use notify::DebouncedWatcher;
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Error, ErrorKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
 
 /// The set of requirements for watcher debounce event handling functions.
 ///
@@ -83,8 +93,12 @@ use notify::{Error, Event, RecommendedWatcher, Watcher};
 ///                     println!("Event {:?} for path {:?}",event.kind,event.path);
 ///                 }
 ///             },
-///             // errors are immediately reported
-///             Err(error) => println!("Got error {:?}",error),
+///             // errors are immediately reported, unless error batching is enabled
+///             Err(errors) => {
+///                 for error in errors {
+///                     println!("Got error {:?}",error);
+///                 }
+///             },
 ///         }
 ///     }
 /// }
@@ -107,6 +121,11 @@ pub trait DebounceEventHandler: Send + 'static {
 pub struct Config {
     timeout: Duration,
     batch_mode: bool,
+    policy: DebouncePolicy,
+    change_kinds: bool,
+    max_paths: Option<usize>,
+    overflow_policy: OverflowPolicy,
+    batch_errors: bool,
     notify_config: notify::Config,
 }
 
@@ -115,6 +134,11 @@ impl Default for Config {
         Self {
             timeout: Duration::from_millis(500),
             batch_mode: true,
+            policy: DebouncePolicy::default(),
+            change_kinds: false,
+            max_paths: None,
+            overflow_policy: OverflowPolicy::default(),
+            batch_errors: false,
             notify_config: notify::Config::default(),
         }
     }
@@ -136,6 +160,46 @@ impl Config {
         self.batch_mode = batch_mode;
         self
     }
+    /// Set the debounce policy
+    ///
+    /// See [`DebouncePolicy`], defaults to [`DebouncePolicy::TrailingEdge`].
+    pub fn with_policy(mut self, policy: DebouncePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+    /// Summarize the raw events of each path
+    ///
+    /// If enabled, [`DebouncedEvent::change`] reports what kind of change happened, see
+    /// [`ChangeKind`]. Disabled by default.
+    pub fn with_change_kinds(mut self, change_kinds: bool) -> Self {
+        self.change_kinds = change_kinds;
+        self
+    }
+    /// Set the maximum number of paths to track
+    ///
+    /// If more paths change within the timeout, the debouncer reacts according to the
+    /// [`OverflowPolicy`]. This bounds the memory used during e.g. a large checkout. Unlimited by
+    /// default.
+    pub fn with_max_paths(mut self, max_paths: Option<usize>) -> Self {
+        self.max_paths = max_paths;
+        self
+    }
+    /// Set what happens if more paths than allowed by [`Config::with_max_paths`] change
+    ///
+    /// See [`OverflowPolicy`], defaults to [`OverflowPolicy::Rescan`].
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+    /// Batch errors instead of passing them on immediately
+    ///
+    /// When enabled, errors are collected and delivered together after the timeout. Errors with
+    /// the same kind and paths as an already collected error are dropped, see
+    /// [`Debouncer::suppressed_errors`]. Disabled by default.
+    pub fn with_error_batching(mut self, batch_errors: bool) -> Self {
+        self.batch_errors = batch_errors;
+        self
+    }
     /// Set [`notify::Config`] for the backend
     pub fn with_notify_config(mut self, notify_config: notify::Config) -> Self {
         self.notify_config = notify_config;
@@ -143,6 +207,48 @@ impl Config {
     }
 }
 
+/// When events are emitted for a path that keeps changing.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum DebouncePolicy {
+    /// Emit an `Any` event once the path didn't change for the timeout.
+    ///
+    /// While the path keeps changing, an `AnyContinuous` event is emitted with each tick after the
+    /// timeout.
+    TrailingEdge,
+    /// Emit an `Any` event for the first change immediately and suppress all further events until
+    /// the path didn't change for the timeout.
+    LeadingEdge,
+    /// Like `TrailingEdge`, but while the path keeps changing, emit an `AnyContinuous` event every
+    /// `max_wait` instead.
+    MaxWait(Duration),
+}
+
+impl Default for DebouncePolicy {
+    fn default() -> Self {
+        Self::TrailingEdge
+    }
+}
+
+/// What happens if more paths change than the debouncer may track.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// Forget all tracked paths and emit a single [`DebouncedEventKind::Rescan`] event once the
+    /// changes stopped for the timeout.
+    ///
+    /// Until then, no other events are emitted.
+    Rescan,
+    /// Emit `Any` events for all tracked paths immediately, regardless of their timeout.
+    Flush,
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        Self::Rescan
+    }
+}
+
 impl<F> DebounceEventHandler for F
 where
     F: FnMut(DebounceEventResult) + Send + 'static,
@@ -172,6 +278,8 @@ struct EventData {
     insert: Instant,
     /// Last Update
     update: Instant,
+    /// Summary of the raw events, if enabled
+    change: Option<ChangeKind>,
 }
 
 impl EventData {
@@ -180,13 +288,17 @@ impl EventData {
         Self {
             insert: time,
             update: time,
+            change: None,
         }
     }
 }
 
 /// A result of debounced events.
-/// Comes with either a vec of events or an immediate error.
-pub type DebounceEventResult = Result<Vec<DebouncedEvent>, Error>;
+/// Comes with either a vec of events or a vec of errors.
+///
+/// Unless [`Config::with_error_batching`] is enabled, each error is delivered on its own and
+/// immediately.
+pub type DebounceEventResult = Result<Vec<DebouncedEvent>, Vec<Error>>;
 
 /// A debounced event kind.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -197,29 +309,98 @@ pub enum DebouncedEventKind {
     Any,
     /// Event but debounce timed out (for example continuous writes)
     AnyContinuous,
+    /// Events were lost or too many paths changed, the path of the event is empty.
+    ///
+    /// Emitted for a raw event that [needs a rescan](notify::Event::need_rescan) and on overflow
+    /// with [`OverflowPolicy::Rescan`]. The paths tracked until then are forgotten and the event
+    /// is emitted once the changes stopped for the timeout. The watched paths should be scanned
+    /// again.
+    Rescan,
+}
+
+/// A summary of the raw events of a path, see [`Config::with_change_kinds`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum ChangeKind {
+    /// The path was created, and possibly modified afterwards
+    Created,
+    /// The content of the path was modified, or it was replaced
+    Modified,
+    /// The path was removed, or renamed to another path
+    Removed,
+    /// The path was renamed, but the backend doesn't report whether it's the old or the new path
+    Renamed,
+    /// Only the metadata of the path was modified
+    Metadata,
+}
+
+impl ChangeKind {
+    /// Summarize a raw event kind for the path at `index` in the paths of the event.
+    ///
+    /// Returns `None` for access events, which don't change anything.
+    fn from_event_kind(kind: &EventKind, index: usize) -> Option<Self> {
+        match kind {
+            EventKind::Access(_) => None,
+            EventKind::Create(_) => Some(Self::Created),
+            EventKind::Remove(_) => Some(Self::Removed),
+            EventKind::Modify(ModifyKind::Metadata(_)) => Some(Self::Metadata),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Some(Self::Removed),
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Some(Self::Created),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if index == 0 => {
+                Some(Self::Removed)
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => Some(Self::Created),
+            EventKind::Modify(ModifyKind::Name(_)) => Some(Self::Renamed),
+            EventKind::Modify(_) | EventKind::Any | EventKind::Other => Some(Self::Modified),
+        }
+    }
+
+    /// Combine the summary of the previous events with a new one.
+    fn merge(previous: Option<Self>, next: Self) -> Self {
+        match (previous, next) {
+            (None, next) => next,
+            // created and removed within the timeout
+            (Some(Self::Created), Self::Removed) => Self::Removed,
+            (Some(Self::Created), _) => Self::Created,
+            // replaced within the timeout
+            (Some(Self::Removed), Self::Created) => Self::Modified,
+            (_, Self::Removed | Self::Created) => next,
+            (Some(Self::Removed | Self::Metadata), next) => next,
+            (Some(previous), _) => previous,
+        }
+    }
 }
 
 /// A debounced event.
 ///
 /// Does not emit any specific event type on purpose, only distinguishes between an any event and a continuous any event.
+/// A summary of the raw events can be enabled with [`Config::with_change_kinds`].
+///
+/// New fields may be added, use [`DebouncedEvent::new`] to construct it.
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub struct DebouncedEvent {
     /// Event path
     pub path: PathBuf,
     /// Event kind
     pub kind: DebouncedEventKind,
+    /// Summary of the raw events of the path since it was first changed, if enabled
+    pub change: Option<ChangeKind>,
 }
 
 impl DebouncedEvent {
+    /// Construct a debounced event.
     #[inline(always)]
-    fn new(path: PathBuf, kind: DebouncedEventKind) -> Self {
-        Self { path, kind }
+    pub fn new(path: PathBuf, kind: DebouncedEventKind, change: Option<ChangeKind>) -> Self {
+        Self { path, kind, change }
     }
 }
 
 enum InnerEvent {
     NotifyEvent(Result<Event, Error>),
+    Flush,
     Shutdown,
 }
 
@@ -231,27 +412,148 @@ struct DebounceDataInner {
     /// Whether to time events exactly, or batch multiple together.
     /// This reduces the amount of updates but possibly waiting longer than necessary for some events
     batch_mode: bool,
+    /// When to emit events, config
+    policy: DebouncePolicy,
+    /// Whether to summarize the raw events, config
+    change_kinds: bool,
+    /// Maximum number of entries in the event map, config
+    max_paths: Option<usize>,
+    /// What to do if the event map is full, config
+    overflow_policy: OverflowPolicy,
+    /// Pending rescan, all other events are ignored until it's emitted
+    rescan: Option<EventData>,
+    /// Size of the event map, shared with the debouncer
+    tracked_paths: Arc<AtomicUsize>,
+    /// Whether to batch errors, config
+    batch_errors: bool,
+    /// Collected errors
+    errors: Vec<Error>,
+    /// Kind and paths of the collected errors
+    error_keys: HashSet<(ErrorKey, Vec<PathBuf>)>,
+    /// Time of the first collected error
+    errors_since: Option<Instant>,
+    /// Number of dropped duplicate errors, shared with the debouncer
+    suppressed_errors: Arc<AtomicUsize>,
     /// next debounce deadline
     debounce_deadline: Option<Instant>,
 }
 
+/// The comparable part of an [`ErrorKind`], used to detect duplicate errors
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ErrorKey {
+    Generic(String),
+    Io(io::ErrorKind, Option<i32>),
+    PathNotFound,
+    WatchNotFound,
+    InvalidConfig(notify::Config),
+    MaxFilesWatch,
+}
+
+impl ErrorKey {
+    fn new(kind: &ErrorKind) -> Self {
+        match kind {
+            ErrorKind::Generic(message) => ErrorKey::Generic(message.clone()),
+            ErrorKind::Io(error) => ErrorKey::Io(error.kind(), error.raw_os_error()),
+            ErrorKind::PathNotFound => ErrorKey::PathNotFound,
+            ErrorKind::WatchNotFound => ErrorKey::WatchNotFound,
+            ErrorKind::InvalidConfig(config) => ErrorKey::InvalidConfig(*config),
+            ErrorKind::MaxFilesWatch => ErrorKey::MaxFilesWatch,
+        }
+    }
+}
+
 impl DebounceDataInner {
-    pub fn new(timeout: Duration, batch_mode: bool) -> Self {
+    pub fn new(config: &Config) -> Self {
         Self {
-            timeout,
+            timeout: config.timeout,
             debounce_deadline: None,
             event_map: Default::default(),
-            batch_mode,
+            batch_mode: config.batch_mode,
+            policy: config.policy,
+            change_kinds: config.change_kinds,
+            max_paths: config.max_paths,
+            overflow_policy: config.overflow_policy,
+            rescan: None,
+            tracked_paths: Arc::new(AtomicUsize::new(0)),
+            batch_errors: config.batch_errors,
+            errors: Vec::new(),
+            error_keys: HashSet::new(),
+            errors_since: None,
+            suppressed_errors: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Publish the size of the event map
+    fn update_tracked_paths(&self) {
+        self.tracked_paths
+            .store(self.event_map.len(), Ordering::Relaxed);
+    }
+
     /// Returns a duration to wait for the next tick
     #[inline]
     pub fn next_tick(&self) -> Option<Duration> {
+        let errors_deadline = self.errors_since.map(|since| since + self.timeout);
         self.debounce_deadline
+            .into_iter()
+            .chain(errors_deadline)
+            .min()
             .map(|deadline| deadline.saturating_duration_since(Instant::now()))
     }
 
+    /// Add an error, returns the errors to emit immediately
+    pub fn add_error(&mut self, error: Error) -> Vec<Error> {
+        if !self.batch_errors {
+            return vec![error];
+        }
+        let key = (ErrorKey::new(&error.kind), error.paths.clone());
+        if self.error_keys.insert(key) {
+            self.errors_since.get_or_insert_with(Instant::now);
+            self.errors.push(error);
+        } else {
+            log::trace!("suppressed duplicate error: {:?}", error);
+            self.suppressed_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        Vec::new()
+    }
+
+    /// Retrieve the collected errors once the timeout elapsed, or immediately if `force` is set
+    pub fn errors(&mut self, force: bool) -> Vec<Error> {
+        match self.errors_since {
+            Some(since) if force || since.elapsed() >= self.timeout => {
+                self.errors_since = None;
+                self.error_keys.clear();
+                std::mem::take(&mut self.errors)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Retrieve a vec of all pending events, regardless of their timeout
+    pub fn flushed_events(&mut self) -> Vec<DebouncedEvent> {
+        let mut events = Vec::with_capacity(self.event_map.len() + 1);
+        self.debounce_deadline = None;
+        if self.rescan.take().is_some() {
+            events.push(DebouncedEvent::new(
+                PathBuf::new(),
+                DebouncedEventKind::Rescan,
+                None,
+            ));
+        }
+        for (path, event) in self.event_map.drain() {
+            if self.policy == DebouncePolicy::LeadingEdge {
+                // already emitted on the first change
+                continue;
+            }
+            events.push(DebouncedEvent::new(
+                path,
+                DebouncedEventKind::Any,
+                event.change,
+            ));
+        }
+        self.update_tracked_paths();
+        events
+    }
+
     /// Retrieve a vec of debounced events, removing them if not continuous
     ///
     /// Updates the internal tracker for the next tick
@@ -261,26 +563,69 @@ impl DebounceDataInner {
         // TODO: perfect fit for drain_filter https://github.com/rust-lang/rust/issues/59618
         // reset deadline
         self.debounce_deadline = None;
-        for (path, event) in self.event_map.drain() {
+        if let Some(rescan) = self.rescan.take() {
+            if rescan.update.elapsed() >= self.timeout {
+                log::trace!("debounced event: {:?}", DebouncedEventKind::Rescan);
+                events_expired.push(DebouncedEvent::new(
+                    PathBuf::new(),
+                    DebouncedEventKind::Rescan,
+                    None,
+                ));
+            } else {
+                Self::check_deadline(
+                    self.batch_mode,
+                    self.timeout,
+                    DebouncePolicy::TrailingEdge,
+                    &mut self.debounce_deadline,
+                    &rescan,
+                );
+                self.rescan = Some(rescan);
+            }
+        }
+        let continuous_timeout = match self.policy {
+            DebouncePolicy::MaxWait(max_wait) => max_wait,
+            _ => self.timeout,
+        };
+        for (path, mut event) in self.event_map.drain() {
             if event.update.elapsed() >= self.timeout {
+                if self.policy == DebouncePolicy::LeadingEdge {
+                    // already emitted on the first change
+                    continue;
+                }
                 log::trace!("debounced event: {:?}", DebouncedEventKind::Any);
-                events_expired.push(DebouncedEvent::new(path, DebouncedEventKind::Any));
-            } else if event.insert.elapsed() >= self.timeout {
+                events_expired.push(DebouncedEvent::new(
+                    path,
+                    DebouncedEventKind::Any,
+                    event.change,
+                ));
+            } else if self.policy != DebouncePolicy::LeadingEdge
+                && event.insert.elapsed() >= continuous_timeout
+            {
                 log::trace!("debounced event: {:?}", DebouncedEventKind::AnyContinuous);
+                if let DebouncePolicy::MaxWait(_) = self.policy {
+                    event.insert = Instant::now();
+                }
                 // set a new deadline, otherwise an 'AnyContinuous' will never resolve to a final 'Any' event
                 Self::check_deadline(
                     self.batch_mode,
                     self.timeout,
+                    self.policy,
                     &mut self.debounce_deadline,
                     &event,
                 );
+                let change = event.change;
                 data_back.insert(path.clone(), event);
-                events_expired.push(DebouncedEvent::new(path, DebouncedEventKind::AnyContinuous));
+                events_expired.push(DebouncedEvent::new(
+                    path,
+                    DebouncedEventKind::AnyContinuous,
+                    change,
+                ));
             } else {
                 // event is neither old enough for continous event, nor is it expired for an Any event
                 Self::check_deadline(
                     self.batch_mode,
                     self.timeout,
+                    self.policy,
                     &mut self.debounce_deadline,
                     &event,
                 );
@@ -288,6 +633,7 @@ impl DebounceDataInner {
             }
         }
         self.event_map = data_back;
+        self.update_tracked_paths();
         events_expired
     }
 
@@ -298,10 +644,14 @@ impl DebounceDataInner {
     fn check_deadline(
         batch_mode: bool,
         timeout: Duration,
+        policy: DebouncePolicy,
         debounce_deadline: &mut Option<Instant>,
         event: &EventData,
     ) {
-        let deadline_candidate = event.update + timeout;
+        let mut deadline_candidate = event.update + timeout;
+        if let DebouncePolicy::MaxWait(max_wait) = policy {
+            deadline_candidate = deadline_candidate.min(event.insert + max_wait);
+        }
         match debounce_deadline {
             Some(current_deadline) => {
                 // shorten deadline to not delay the event
@@ -315,20 +665,76 @@ impl DebounceDataInner {
     }
 
     /// Add new event to debouncer cache
+    ///
+    /// Returns the events to emit immediately, with the leading edge policy.
     #[inline(always)]
-    fn add_event(&mut self, event: Event) {
+    fn add_event(&mut self, event: Event) -> Vec<DebouncedEvent> {
         log::trace!("raw event: {event:?}");
         let time = Instant::now();
         if self.debounce_deadline.is_none() {
-            self.debounce_deadline = Some(time + self.timeout);
+            self.debounce_deadline = Some(match self.policy {
+                DebouncePolicy::MaxWait(max_wait) => time + max_wait.min(self.timeout),
+                _ => time + self.timeout,
+            });
         }
-        for path in event.paths.into_iter() {
+        let mut leading_events = Vec::new();
+        if event.need_rescan() {
+            self.start_rescan(time);
+        }
+        if let Some(rescan) = &mut self.rescan {
+            rescan.update = time;
+            self.update_tracked_paths();
+            return leading_events;
+        }
+        for (index, path) in event.paths.into_iter().enumerate() {
+            let change = if self.change_kinds {
+                ChangeKind::from_event_kind(&event.kind, index)
+            } else {
+                None
+            };
             if let Some(v) = self.event_map.get_mut(&path) {
                 v.update = time;
+                if let Some(change) = change {
+                    v.change = Some(ChangeKind::merge(v.change, change));
+                }
             } else {
-                self.event_map.insert(path, EventData::new_any(time));
+                let mut data = EventData::new_any(time);
+                data.change = change;
+                if self.policy == DebouncePolicy::LeadingEdge {
+                    log::trace!("debounced event: {:?}", DebouncedEventKind::Any);
+                    leading_events.push(DebouncedEvent::new(
+                        path.clone(),
+                        DebouncedEventKind::Any,
+                        change,
+                    ));
+                }
+                self.event_map.insert(path, data);
             }
         }
+        if matches!(self.max_paths, Some(max_paths) if self.event_map.len() > max_paths) {
+            match self.overflow_policy {
+                OverflowPolicy::Rescan => self.start_rescan(time),
+                OverflowPolicy::Flush => {
+                    log::trace!("flushing {} paths", self.event_map.len());
+                    let is_leading_edge = self.policy == DebouncePolicy::LeadingEdge;
+                    leading_events.extend(self.event_map.drain().filter_map(|(path, event)| {
+                        // with the leading edge policy, the events were emitted already
+                        (!is_leading_edge).then(|| {
+                            DebouncedEvent::new(path, DebouncedEventKind::Any, event.change)
+                        })
+                    }));
+                }
+            }
+        }
+        self.update_tracked_paths();
+        leading_events
+    }
+
+    /// Forget all tracked paths and emit a rescan event once the changes stopped
+    fn start_rescan(&mut self, time: Instant) {
+        log::trace!("rescan, forgetting {} paths", self.event_map.len());
+        self.event_map.clear();
+        self.rescan = Some(EventData::new_any(time));
     }
 }
 
@@ -337,13 +743,59 @@ impl DebounceDataInner {
 pub struct Debouncer<T: Watcher> {
     watcher: T,
     stop_channel: Sender<InnerEvent>,
+    debouncer_thread: Option<JoinHandle<()>>,
+    tracked_paths: Arc<AtomicUsize>,
+    suppressed_errors: Arc<AtomicUsize>,
 }
 
 impl<T: Watcher> Debouncer<T> {
     /// Access to the internally used notify Watcher backend
-    pub fn watcher(&mut self) -> &mut dyn Watcher {
+    pub fn watcher(&mut self) -> &mut T {
         &mut self.watcher
     }
+
+    /// Stop the debouncer, waits for the event thread to finish.
+    ///
+    /// All pending events are emitted before the thread exits.
+    pub fn stop(mut self) {
+        // send error just means that it is stopped already
+        let _ = self.stop_channel.send(InnerEvent::Flush);
+        let _ = self.stop_channel.send(InnerEvent::Shutdown);
+        if let Some(t) = self.debouncer_thread.take() {
+            let _ = t.join();
+        }
+    }
+
+    /// Emit all pending events immediately, regardless of their timeout.
+    ///
+    /// Events received by the debouncer before this call are included.
+    pub fn flush(&self) {
+        let _ = self.stop_channel.send(InnerEvent::Flush);
+    }
+
+    /// Returns the number of paths waiting for their timeout
+    ///
+    /// The value is updated by the debouncer thread, so it may lag behind slightly.
+    pub fn tracked_paths(&self) -> usize {
+        self.tracked_paths.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of duplicate errors dropped so far
+    ///
+    /// Only counts if [`Config::with_error_batching`] is enabled.
+    pub fn suppressed_errors(&self) -> usize {
+        self.suppressed_errors.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Watcher> DebouncedWatcher for Debouncer<T> {
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<(), Error> {
+        self.watcher.watch(path, recursive_mode)
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<(), Error> {
+        self.watcher.unwatch(path)
+    }
 }
 
 impl<T: Watcher> Drop for Debouncer<T> {
@@ -360,41 +812,30 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher>(
 ) -> Result<Debouncer<T>, Error> {
     let (tx, rx) = std::sync::mpsc::channel();
 
-    std::thread::Builder::new()
+    let mut data = DebounceDataInner::new(&config);
+    let tracked_paths = data.tracked_paths.clone();
+    let suppressed_errors = data.suppressed_errors.clone();
+    let thread = std::thread::Builder::new()
         .name("notify-rs debouncer loop".to_string())
-        .spawn(move || {
-            let mut data = DebounceDataInner::new(config.timeout, config.batch_mode);
-            let mut run = true;
-            while run {
-                match data.next_tick() {
-                    Some(timeout) => {
-                        // wait for wakeup
-                        match rx.recv_timeout(timeout) {
-                            Ok(InnerEvent::NotifyEvent(event_result)) => match event_result {
-                                Ok(event) => data.add_event(event),
-                                Err(err) => event_handler.handle_event(Err(err)),
-                            },
-                            Err(RecvTimeoutError::Timeout) => {
-                                let send_data = data.debounced_events();
-                                if !send_data.is_empty() {
-                                    event_handler.handle_event(Ok(send_data));
-                                }
-                            }
-                            Ok(InnerEvent::Shutdown) | Err(RecvTimeoutError::Disconnected) => {
-                                run = false
-                            }
-                        }
-                    }
-                    None => match rx.recv() {
-                        // no timeout, wait for event
-                        Ok(InnerEvent::NotifyEvent(e)) => match e {
-                            Ok(event) => data.add_event(event),
-                            Err(err) => event_handler.handle_event(Err(err)),
-                        },
-                        Ok(InnerEvent::Shutdown) => run = false,
-                        Err(_) => run = false,
-                    },
-                }
+        .spawn(move || loop {
+            let received = match data.next_tick() {
+                // wait for wakeup
+                Some(timeout) => rx.recv_timeout(timeout),
+                // no timeout, wait for event
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let (send_data, errors) = match received {
+                Ok(InnerEvent::NotifyEvent(Ok(event))) => (data.add_event(event), Vec::new()),
+                Ok(InnerEvent::NotifyEvent(Err(err))) => (Vec::new(), data.add_error(err)),
+                Ok(InnerEvent::Flush) => (data.flushed_events(), data.errors(true)),
+                Err(RecvTimeoutError::Timeout) => (data.debounced_events(), data.errors(false)),
+                Ok(InnerEvent::Shutdown) | Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if !send_data.is_empty() {
+                event_handler.handle_event(Ok(send_data));
+            }
+            if !errors.is_empty() {
+                event_handler.handle_event(Err(errors));
             }
         })?;
 
@@ -411,6 +852,9 @@ pub fn new_debouncer_opt<F: DebounceEventHandler, T: Watcher>(
     let guard = Debouncer {
         watcher,
         stop_channel: tx,
+        debouncer_thread: Some(thread),
+        tracked_paths,
+        suppressed_errors,
     };
 
     Ok(guard)
@@ -426,3 +870,288 @@ pub fn new_debouncer<F: DebounceEventHandler>(
     let config = Config::default().with_timeout(timeout);
     new_debouncer_opt::<F, RecommendedWatcher>(config, event_handler)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, DataChange, MetadataKind, RemoveKind};
+    use notify::{memory::MemoryFs, MemoryWatcher, RecursiveMode};
+    use std::path::Path;
+
+    fn summarize(kinds: &[EventKind]) -> Option<ChangeKind> {
+        kinds.iter().fold(None, |change, kind| {
+            match ChangeKind::from_event_kind(kind, 0) {
+                Some(next) => Some(ChangeKind::merge(change, next)),
+                None => change,
+            }
+        })
+    }
+
+    fn event(path: &str) -> Event {
+        Event::new(EventKind::Any).add_path(path.into())
+    }
+
+    #[test]
+    fn overflow_rescan() {
+        let config = Config::default()
+            .with_timeout(Duration::ZERO)
+            .with_max_paths(Some(2));
+        let mut data = DebounceDataInner::new(&config);
+
+        assert!(data.add_event(event("/a")).is_empty());
+        assert!(data.add_event(event("/b")).is_empty());
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 2);
+        assert!(data.add_event(event("/c")).is_empty());
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 0);
+        assert!(data.add_event(event("/d")).is_empty());
+
+        assert_eq!(
+            data.debounced_events(),
+            vec![DebouncedEvent::new(
+                PathBuf::new(),
+                DebouncedEventKind::Rescan,
+                None
+            )]
+        );
+        assert!(data.add_event(event("/e")).is_empty());
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn need_rescan() {
+        let config = Config::default().with_timeout(Duration::ZERO);
+        let mut data = DebounceDataInner::new(&config);
+
+        assert!(data.add_event(event("/a")).is_empty());
+        let rescan = Event::new(EventKind::Other).set_flag(notify::event::Flag::Rescan);
+        assert!(data.add_event(rescan).is_empty());
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 0);
+        assert!(data.add_event(event("/b")).is_empty());
+
+        assert_eq!(
+            data.debounced_events(),
+            vec![DebouncedEvent::new(
+                PathBuf::new(),
+                DebouncedEventKind::Rescan,
+                None
+            )]
+        );
+        assert!(data.add_event(event("/c")).is_empty());
+        assert_eq!(
+            data.debounced_events(),
+            vec![DebouncedEvent::new(
+                PathBuf::from("/c"),
+                DebouncedEventKind::Any,
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn overflow_flush() {
+        let config = Config::default()
+            .with_max_paths(Some(1))
+            .with_overflow_policy(OverflowPolicy::Flush);
+        let mut data = DebounceDataInner::new(&config);
+
+        assert!(data.add_event(event("/a")).is_empty());
+        let mut events = data.add_event(event("/b"));
+        events.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            events,
+            vec![
+                DebouncedEvent::new("/a".into(), DebouncedEventKind::Any, None),
+                DebouncedEvent::new("/b".into(), DebouncedEventKind::Any, None)
+            ]
+        );
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn leading_edge_policy() {
+        let config = Config::default()
+            .with_timeout(Duration::from_secs(60))
+            .with_policy(DebouncePolicy::LeadingEdge);
+        let mut data = DebounceDataInner::new(&config);
+        let any = |path: &str| DebouncedEvent::new(path.into(), DebouncedEventKind::Any, None);
+
+        assert_eq!(data.add_event(event("/a")), vec![any("/a")]);
+        assert!(data.add_event(event("/a")).is_empty());
+        assert_eq!(data.add_event(event("/b")), vec![any("/b")]);
+
+        // no continuous or trailing events
+        assert!(data.debounced_events().is_empty());
+        assert!(data.flushed_events().is_empty());
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 0);
+
+        // a new burst starts once the path was quiet for the timeout
+        let config = config.with_timeout(Duration::ZERO);
+        let mut data = DebounceDataInner::new(&config);
+        assert_eq!(data.add_event(event("/a")), vec![any("/a")]);
+        assert!(data.debounced_events().is_empty());
+        assert_eq!(data.add_event(event("/a")), vec![any("/a")]);
+    }
+
+    #[test]
+    fn max_wait_policy() {
+        let config = Config::default()
+            .with_timeout(Duration::from_secs(120))
+            .with_policy(DebouncePolicy::MaxWait(Duration::from_secs(60)));
+        let mut data = DebounceDataInner::new(&config);
+
+        assert!(data.add_event(event("/a")).is_empty());
+        assert!(data.next_tick() <= Some(Duration::from_secs(60)));
+        assert!(data.debounced_events().is_empty());
+
+        // the path keeps changing, it's emitted every time the max wait elapsed
+        let config = config.with_policy(DebouncePolicy::MaxWait(Duration::ZERO));
+        let mut data = DebounceDataInner::new(&config);
+        let continuous = vec![DebouncedEvent::new(
+            "/a".into(),
+            DebouncedEventKind::AnyContinuous,
+            None,
+        )];
+        assert!(data.add_event(event("/a")).is_empty());
+        assert_eq!(data.debounced_events(), continuous);
+        assert!(data.add_event(event("/a")).is_empty());
+        assert_eq!(data.debounced_events(), continuous);
+        assert_eq!(
+            data.flushed_events(),
+            vec![DebouncedEvent::new(
+                "/a".into(),
+                DebouncedEventKind::Any,
+                None
+            )]
+        );
+    }
+
+    #[test]
+    fn flushed_events() {
+        let config = Config::default().with_timeout(Duration::from_secs(60));
+        let mut data = DebounceDataInner::new(&config);
+
+        assert!(data.add_event(event("/a")).is_empty());
+        assert!(data.debounced_events().is_empty());
+        assert!(data.next_tick().is_some());
+
+        assert_eq!(
+            data.flushed_events(),
+            vec![DebouncedEvent::new(
+                "/a".into(),
+                DebouncedEventKind::Any,
+                None
+            )]
+        );
+        assert_eq!(data.next_tick(), None);
+        assert_eq!(data.tracked_paths.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn stop_emits_pending_events() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let config = Config::default().with_timeout(Duration::from_secs(60));
+        let mut debouncer = new_debouncer_opt::<_, MemoryWatcher>(config, tx).unwrap();
+        debouncer.watcher().set_fs(&fs);
+        debouncer
+            .watch(Path::new("/watch"), RecursiveMode::Recursive)
+            .unwrap();
+        let paths = |result: DebounceEventResult| -> Vec<PathBuf> {
+            result
+                .unwrap()
+                .into_iter()
+                .map(|event| event.path)
+                .collect()
+        };
+
+        fs.write("/watch/flushed", b"a").unwrap();
+        debouncer.flush();
+        let result = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(paths(result), vec![PathBuf::from("/watch/flushed")]);
+
+        // the events are emitted before `stop` returns
+        fs.write("/watch/stopped", b"a").unwrap();
+        debouncer.stop();
+        assert_eq!(
+            paths(rx.try_recv().unwrap()),
+            vec![PathBuf::from("/watch/stopped")]
+        );
+    }
+
+    #[test]
+    fn error_batching() {
+        let config = Config::default()
+            .with_timeout(Duration::from_secs(60))
+            .with_error_batching(true);
+        let mut data = DebounceDataInner::new(&config);
+        let error = |path: &str| Error::path_not_found().add_path(path.into());
+
+        assert!(data.add_error(error("/a")).is_empty());
+        assert!(data.add_error(error("/a")).is_empty());
+        assert!(data.add_error(error("/b")).is_empty());
+        assert!(data.add_error(Error::generic("/a")).is_empty());
+        assert_eq!(data.suppressed_errors.load(Ordering::Relaxed), 1);
+        assert!(data.next_tick().is_some());
+        assert!(data.errors(false).is_empty());
+
+        let errors: Vec<_> = data.errors(true).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            vec![
+                error("/a").to_string(),
+                error("/b").to_string(),
+                "/a".to_string()
+            ]
+        );
+        assert_eq!(data.next_tick(), None);
+
+        // the window starts over
+        assert!(data.add_error(error("/a")).is_empty());
+        assert_eq!(data.errors(true).len(), 1);
+
+        // I/O errors are compared by kind and OS error code
+        let io_error = |kind: io::ErrorKind| Error::io(io::Error::new(kind, "message"));
+        data.add_error(io_error(io::ErrorKind::PermissionDenied));
+        data.add_error(io_error(io::ErrorKind::PermissionDenied));
+        data.add_error(io_error(io::ErrorKind::NotFound));
+        assert_eq!(data.suppressed_errors.load(Ordering::Relaxed), 2);
+        assert_eq!(data.errors(true).len(), 2);
+    }
+
+    #[test]
+    fn change_kinds() {
+        let create = EventKind::Create(CreateKind::File);
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        let metadata = EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any));
+        let remove = EventKind::Remove(RemoveKind::File);
+        let close = EventKind::Access(AccessKind::Any);
+
+        assert_eq!(summarize(&[close]), None);
+        assert_eq!(
+            summarize(&[create, modify, close]),
+            Some(ChangeKind::Created)
+        );
+        assert_eq!(summarize(&[create, remove]), Some(ChangeKind::Removed));
+        assert_eq!(summarize(&[modify, remove]), Some(ChangeKind::Removed));
+        assert_eq!(summarize(&[remove, create]), Some(ChangeKind::Modified));
+        assert_eq!(summarize(&[metadata]), Some(ChangeKind::Metadata));
+        assert_eq!(summarize(&[metadata, modify]), Some(ChangeKind::Modified));
+        assert_eq!(summarize(&[modify, metadata]), Some(ChangeKind::Modified));
+        assert_eq!(
+            summarize(&[EventKind::Modify(ModifyKind::Name(RenameMode::Any))]),
+            Some(ChangeKind::Renamed)
+        );
+
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Both));
+        assert_eq!(
+            ChangeKind::from_event_kind(&rename, 0),
+            Some(ChangeKind::Removed)
+        );
+        assert_eq!(
+            ChangeKind::from_event_kind(&rename, 1),
+            Some(ChangeKind::Created)
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Merging of duplicate events within one backend batch
+
+use crate::{Event, EventHandler, Result};
+use std::{collections::HashMap, path::PathBuf};
+
+/// Events of one backend batch, e.g. one inotify read or one poll pass
+///
+/// If coalescing is enabled, the events are held back until [`EventBatch::flush`] and an event is
+/// dropped if it's equal to the previous event for the same paths. Events for other paths in
+/// between don't matter, but a different event for the same paths does, so e.g. a `Create`,
+/// `Remove`, `Create` sequence is kept. Errors are never merged.
+///
+/// If coalescing is disabled, all events are passed through immediately.
+#[derive(Debug, Default)]
+pub(crate) struct EventBatch {
+    coalesce: bool,
+    events: Vec<Result<Event>>,
+    /// Index into `events` of the last event for each set of paths
+    last_by_paths: HashMap<Vec<PathBuf>, usize>,
+}
+
+impl EventBatch {
+    pub fn new(coalesce: bool) -> Self {
+        Self {
+            coalesce,
+            events: Vec::new(),
+            last_by_paths: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, event: Result<Event>, event_handler: &mut dyn EventHandler) {
+        if !self.coalesce {
+            event_handler.handle_event(event);
+            return;
+        }
+
+        if let Ok(ref event) = event {
+            let previous = self
+                .last_by_paths
+                .get(&event.paths)
+                .and_then(|&index| self.events[index].as_ref().ok());
+            if previous == Some(event) {
+                return;
+            }
+            self.last_by_paths
+                .insert(event.paths.clone(), self.events.len());
+        }
+
+        self.events.push(event);
+    }
+
+    pub fn flush(&mut self, event_handler: &mut dyn EventHandler) {
+        self.last_by_paths.clear();
+        for event in self.events.drain(..) {
+            event_handler.handle_event(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, DataChange, EventKind, ModifyKind, RemoveKind};
+    use crate::Error;
+    use std::sync::mpsc;
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(path.into())
+    }
+
+    #[test]
+    fn merges_duplicates_per_path() {
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let (mut tx, rx) = mpsc::channel();
+        let mut batch = EventBatch::new(true);
+
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        batch.push(Ok(event(modify, "/b")), &mut tx);
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        batch.push(Err(Error::generic("error")), &mut tx);
+        batch.push(Err(Error::generic("error")), &mut tx);
+        batch.push(
+            Ok(event(EventKind::Create(CreateKind::File), "/c")),
+            &mut tx,
+        );
+        batch.push(
+            Ok(event(EventKind::Remove(RemoveKind::File), "/c")),
+            &mut tx,
+        );
+        batch.push(
+            Ok(event(EventKind::Create(CreateKind::File), "/c")),
+            &mut tx,
+        );
+        assert!(rx.try_recv().is_err());
+
+        batch.flush(&mut tx);
+        let events: Vec<_> = rx
+            .try_iter()
+            .map(|e| e.map(|e| (e.kind, e.paths)).map_err(|e| e.to_string()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                Ok((modify, vec!["/a".into()])),
+                Ok((modify, vec!["/b".into()])),
+                Err("error".to_string()),
+                Err("error".to_string()),
+                Ok((EventKind::Create(CreateKind::File), vec!["/c".into()])),
+                Ok((EventKind::Remove(RemoveKind::File), vec!["/c".into()])),
+                Ok((EventKind::Create(CreateKind::File), vec!["/c".into()])),
+            ]
+        );
+    }
+
+    #[test]
+    fn flush_starts_a_new_batch() {
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let (mut tx, rx) = mpsc::channel();
+        let mut batch = EventBatch::new(true);
+
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        batch.flush(&mut tx);
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        batch.flush(&mut tx);
+        assert_eq!(rx.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn passes_through_without_coalescing() {
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Any));
+        let (mut tx, rx) = mpsc::channel();
+        let mut batch = EventBatch::new(false);
+
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        batch.push(Ok(event(modify, "/a")), &mut tx);
+        assert_eq!(rx.try_iter().count(), 2);
+    }
+}
@@ -42,6 +42,12 @@ pub struct Config {
 
     /// See [BackendConfig::with_compare_contents]
     compare_contents: bool,
+
+    /// See [Config::with_rename_timeout]
+    rename_timeout: Duration,
+
+    /// See [Config::with_event_coalescing]
+    event_coalescing: bool,
 }
 
 impl Config {
@@ -94,6 +100,47 @@ impl Config {
     pub fn compare_contents(&self) -> bool {
         self.compare_contents
     }
+
+    /// For the [INotifyWatcher](crate::INotifyWatcher) backend.
+    ///
+    /// Time to wait for the second half of a rename. Renames are matched by their cookie, any
+    /// number of them can be in flight at the same time. If no matching `To` event arrives in
+    /// time, the file was moved out of the watched tree and a `Remove` event is emitted for the
+    /// `From` path.
+    ///
+    /// This can't be changed during runtime. 100 milliseconds by default.
+    pub fn with_rename_timeout(mut self, rename_timeout: Duration) -> Self {
+        self.rename_timeout = rename_timeout;
+        self
+    }
+
+    /// Returns current setting
+    pub fn rename_timeout(&self) -> Duration {
+        self.rename_timeout
+    }
+
+    /// For the [INotifyWatcher](crate::INotifyWatcher), [PollWatcher](crate::PollWatcher) and
+    #[cfg_attr(
+        any(test, feature = "memory"),
+        doc = "[MemoryWatcher](crate::MemoryWatcher) backends."
+    )]
+    #[cfg_attr(not(any(test, feature = "memory")), doc = "`MemoryWatcher` backends.")]
+    ///
+    /// Merge duplicate events before passing them to the event handler. Only events of the same
+    /// batch are merged, i.e. of one inotify read or one poll pass, so no delay is added. An event
+    /// is dropped if it's equal to the previous event for the same paths, which reduces the number
+    /// of handler calls on bursty writes.
+    ///
+    /// This can't be changed during runtime. Off by default.
+    pub fn with_event_coalescing(mut self, event_coalescing: bool) -> Self {
+        self.event_coalescing = event_coalescing;
+        self
+    }
+
+    /// Returns current setting
+    pub fn event_coalescing(&self) -> bool {
+        self.event_coalescing
+    }
 }
 
 impl Default for Config {
@@ -101,6 +148,8 @@ impl Default for Config {
         Self {
             poll_interval: Some(Duration::from_secs(30)),
             compare_contents: false,
+            rename_timeout: Duration::from_millis(100),
+            event_coalescing: false,
         }
     }
 }
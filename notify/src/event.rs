@@ -11,6 +11,8 @@ use std::{
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+pub mod format;
+
 /// An event describing open or close operations on files.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -466,6 +468,11 @@ impl EventAttributes {
         self.inner_mut().info = Some(info.to_string());
     }
 
+    /// Sets the source onto the event.
+    pub fn set_source(&mut self, source: &str) {
+        self.inner_mut().source = Some(source.to_string());
+    }
+
     /// Sets the process id onto the event.
     pub fn set_process_id(&mut self, process_id: u32) {
         self.inner_mut().process_id = Some(process_id)
@@ -570,6 +577,12 @@ impl Event {
         self
     }
 
+    /// Sets the source onto the event.
+    pub fn set_source(mut self, source: &str) -> Self {
+        self.attrs.set_source(source);
+        self
+    }
+
     /// Sets the process id onto the event.
     pub fn set_process_id(mut self, process_id: u32) -> Self {
         self.attrs.set_process_id(process_id);
@@ -0,0 +1,820 @@
+//! Line based textual formats for [`Event`]s.
+//!
+//! Two formats are provided, both encoding exactly one event per line:
+//!
+//! - A compact text form, intended to be read by humans and machines alike, see [`to_text`]
+//!   and [`from_text`].
+//! - [JSON Lines](https://jsonlines.org), using the serde representation of [`Event`], see
+//!   [`to_json_line`] and [`from_json_line`]. This requires the `serde` feature.
+//!
+//! Both formats round-trip: decoding an encoded event yields an event equal to the original.
+//! Note that only the attributes considered by [`Event`]'s `PartialEq` implementation are
+//! encoded, the experimental process ID is not.
+//!
+//! # Text format
+//!
+//! A line consists of the event kind, optional attributes in square brackets and the paths, all
+//! separated by a single space:
+//!
+//! ```text
+//! modify(data) /path/to/file
+//! modify(rename(both)) [tracker=7] /path/from /path/to
+//! other [flag=rescan]
+//! remove(file) [info="override" source="inotify"] "/path with spaces"
+//! ```
+//!
+//! The kind mirrors the [`EventKind`] hierarchy using the same names as the serde representation.
+//! Trailing `any` sub kinds are omitted, `access(open)` is thus `Access(Open(Any))` and `modify`
+//! is `Modify(Any)`. When decoding, an explicit `(any)` is accepted as well.
+//!
+//! Paths are written as is, unless they are empty, contain whitespace, control characters,
+//! quotes or backslashes or start with a `[`. In that case they are quoted and escaped like
+//! Rust string literals. On Unix, bytes that are not valid UTF-8 are escaped as `\xNN`.
+//!
+//! ```
+//! use notify::event::{format, DataChange, Event, EventKind, ModifyKind};
+//!
+//! let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+//!     .add_path("/path/to/file".into());
+//!
+//! let line = format::to_text(&event);
+//! assert_eq!(line, "modify(data) /path/to/file");
+//! assert_eq!(format::from_text(&line).unwrap(), event);
+//! ```
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use super::*;
+use crate::{Error, Result};
+
+/// A line based event format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Format {
+    /// The compact text form, see [`to_text`].
+    Text,
+
+    /// JSON Lines, see [`to_json_line`].
+    #[cfg(feature = "serde")]
+    Json,
+}
+
+impl Format {
+    /// Encodes an event as a single line, without the trailing newline.
+    pub fn encode(&self, event: &Event) -> Result<String> {
+        match self {
+            Format::Text => Ok(to_text(event)),
+            #[cfg(feature = "serde")]
+            Format::Json => to_json_line(event),
+        }
+    }
+
+    /// Decodes an event from a single line, surrounding whitespace is ignored.
+    pub fn decode(&self, line: &str) -> Result<Event> {
+        match self {
+            Format::Text => from_text(line),
+            #[cfg(feature = "serde")]
+            Format::Json => from_json_line(line),
+        }
+    }
+}
+
+/// Encodes an event in the compact text form.
+///
+/// See the [module documentation](self) for a description of the format.
+pub fn to_text(event: &Event) -> String {
+    let mut line = kind_to_text(&event.kind);
+
+    let mut attrs = Vec::new();
+    if let Some(tracker) = event.tracker() {
+        attrs.push(format!("tracker={}", tracker));
+    }
+    if let Some(flag) = event.flag() {
+        attrs.push(format!("flag={}", flag_to_text(flag)));
+    }
+    if let Some(info) = event.info() {
+        attrs.push(format!("info={}", quote(info.as_bytes())));
+    }
+    if let Some(source) = event.source() {
+        attrs.push(format!("source={}", quote(source.as_bytes())));
+    }
+    if !attrs.is_empty() {
+        let _ = write!(line, " [{}]", attrs.join(" "));
+    }
+
+    for path in &event.paths {
+        line.push(' ');
+        line.push_str(&path_to_text(path));
+    }
+
+    line
+}
+
+/// Decodes an event from the compact text form.
+///
+/// Surrounding whitespace is ignored. See the [module documentation](self) for a description of
+/// the format.
+pub fn from_text(line: &str) -> Result<Event> {
+    let mut parser = Parser::new(line.trim());
+
+    let kind = parser.word();
+    let mut event = Event::new(kind_from_text(kind)?);
+
+    parser.skip_space();
+    if parser.eat(b'[') {
+        loop {
+            parser.skip_space();
+            if parser.eat(b']') {
+                break;
+            }
+            let key = parser.key()?;
+            match key {
+                "tracker" => {
+                    let value = parser.word();
+                    let tracker = value
+                        .parse()
+                        .map_err(|_| parser.error(&format!("invalid tracker `{}`", value)))?;
+                    event = event.set_tracker(tracker);
+                }
+                "flag" => {
+                    let value = parser.word();
+                    event = event.set_flag(
+                        flag_from_text(value)
+                            .ok_or_else(|| parser.error(&format!("unknown flag `{}`", value)))?,
+                    );
+                }
+                "info" => {
+                    let value = parser.string()?;
+                    event = event.set_info(&value);
+                }
+                "source" => {
+                    let value = parser.string()?;
+                    event = event.set_source(&value);
+                }
+                _ => return Err(parser.error(&format!("unknown attribute `{}`", key))),
+            }
+        }
+    }
+
+    loop {
+        parser.skip_space();
+        if parser.is_empty() {
+            break;
+        }
+        event = event.add_path(parser.path()?);
+    }
+
+    Ok(event)
+}
+
+/// Encodes an event as a single JSON line, without the trailing newline.
+///
+/// The line contains the serde representation of [`Event`]. Fails if a path is not valid UTF-8.
+#[cfg(feature = "serde")]
+pub fn to_json_line(event: &Event) -> Result<String> {
+    serde_json::to_string(event).map_err(|e| Error::generic(&e.to_string()))
+}
+
+/// Decodes an event from a single JSON line.
+#[cfg(feature = "serde")]
+pub fn from_json_line(line: &str) -> Result<Event> {
+    serde_json::from_str(line).map_err(|e| Error::generic(&e.to_string()))
+}
+
+/// Quotes and escapes a string like the string attributes of the compact text form.
+///
+/// ```
+/// use notify::event::format;
+///
+/// let quoted = format::quote_str("say \"hi\"\n");
+/// assert_eq!(quoted, r#""say \"hi\"\n""#);
+/// assert_eq!(format::unquote_str(&quoted).unwrap(), "say \"hi\"\n");
+/// ```
+pub fn quote_str(s: &str) -> String {
+    quote(s.as_bytes())
+}
+
+/// Decodes a string quoted with [`quote_str`], surrounding whitespace is ignored.
+pub fn unquote_str(quoted: &str) -> Result<String> {
+    let mut parser = Parser::new(quoted.trim());
+    let s = parser.string()?;
+    if !parser.is_empty() {
+        return Err(parser.error("unexpected characters after the string"));
+    }
+    Ok(s)
+}
+
+/// Encodes a path as in the compact text form, it is only quoted if necessary.
+///
+/// Unlike [`Path::display`], this is lossless: bytes that are not valid UTF-8 are escaped.
+pub fn path_to_text(path: &Path) -> String {
+    let bytes = path_bytes(path);
+    let bare = std::str::from_utf8(&bytes)
+        .ok()
+        .filter(|s| {
+            !s.is_empty()
+                && !s.starts_with('[')
+                && !s
+                    .chars()
+                    .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '\\')
+        })
+        .map(str::to_string);
+
+    bare.unwrap_or_else(|| quote(&bytes))
+}
+
+/// Encodes an event kind as used in the compact text form, e.g. `modify(data)`.
+pub fn kind_to_text(kind: &EventKind) -> String {
+    let (name, sub) = match kind {
+        EventKind::Any => ("any", None),
+        EventKind::Access(access) => (
+            "access",
+            match access {
+                AccessKind::Any => None,
+                AccessKind::Read => Some("read".to_string()),
+                AccessKind::Open(mode) => Some(nested("open", access_mode_to_text(mode))),
+                AccessKind::Close(mode) => Some(nested("close", access_mode_to_text(mode))),
+                AccessKind::Other => Some("other".to_string()),
+            },
+        ),
+        EventKind::Create(create) => (
+            "create",
+            match create {
+                CreateKind::Any => None,
+                CreateKind::File => Some("file".to_string()),
+                CreateKind::Folder => Some("folder".to_string()),
+                CreateKind::Other => Some("other".to_string()),
+            },
+        ),
+        EventKind::Modify(modify) => (
+            "modify",
+            match modify {
+                ModifyKind::Any => None,
+                ModifyKind::Data(change) => Some(nested(
+                    "data",
+                    match change {
+                        DataChange::Any => None,
+                        DataChange::Size => Some("size"),
+                        DataChange::Content => Some("content"),
+                        DataChange::Other => Some("other"),
+                    },
+                )),
+                ModifyKind::Metadata(metadata) => Some(nested(
+                    "metadata",
+                    match metadata {
+                        MetadataKind::Any => None,
+                        MetadataKind::AccessTime => Some("access-time"),
+                        MetadataKind::WriteTime => Some("write-time"),
+                        MetadataKind::Permissions => Some("permissions"),
+                        MetadataKind::Ownership => Some("ownership"),
+                        MetadataKind::Extended => Some("extended"),
+                        MetadataKind::Other => Some("other"),
+                    },
+                )),
+                ModifyKind::Name(mode) => Some(nested(
+                    "rename",
+                    match mode {
+                        RenameMode::Any => None,
+                        RenameMode::To => Some("to"),
+                        RenameMode::From => Some("from"),
+                        RenameMode::Both => Some("both"),
+                        RenameMode::Other => Some("other"),
+                    },
+                )),
+                ModifyKind::Other => Some("other".to_string()),
+            },
+        ),
+        EventKind::Remove(remove) => (
+            "remove",
+            match remove {
+                RemoveKind::Any => None,
+                RemoveKind::File => Some("file".to_string()),
+                RemoveKind::Folder => Some("folder".to_string()),
+                RemoveKind::Other => Some("other".to_string()),
+            },
+        ),
+        EventKind::Other => ("other", None),
+    };
+
+    match sub {
+        Some(sub) => format!("{}({})", name, sub),
+        None => name.to_string(),
+    }
+}
+
+/// Decodes an event kind as used in the compact text form, e.g. `modify(data)`.
+pub fn kind_from_text(text: &str) -> Result<EventKind> {
+    let unknown = || Error::generic(&format!("unknown event kind `{}`", text));
+
+    let (name, sub) = split_kind(text).ok_or_else(unknown)?;
+    let kind = match (name, sub) {
+        ("any", None) => EventKind::Any,
+        ("other", None) => EventKind::Other,
+        ("access", sub) => EventKind::Access(match split_sub(sub).ok_or_else(unknown)? {
+            ("any", None) => AccessKind::Any,
+            ("read", None) => AccessKind::Read,
+            ("open", mode) => AccessKind::Open(access_mode_from_text(mode).ok_or_else(unknown)?),
+            ("close", mode) => AccessKind::Close(access_mode_from_text(mode).ok_or_else(unknown)?),
+            ("other", None) => AccessKind::Other,
+            _ => return Err(unknown()),
+        }),
+        ("create", sub) => EventKind::Create(match split_sub(sub).ok_or_else(unknown)? {
+            ("any", None) => CreateKind::Any,
+            ("file", None) => CreateKind::File,
+            ("folder", None) => CreateKind::Folder,
+            ("other", None) => CreateKind::Other,
+            _ => return Err(unknown()),
+        }),
+        ("modify", sub) => EventKind::Modify(match split_sub(sub).ok_or_else(unknown)? {
+            ("any", None) => ModifyKind::Any,
+            ("data", sub) => ModifyKind::Data(match split_sub(sub).ok_or_else(unknown)? {
+                ("any", None) => DataChange::Any,
+                ("size", None) => DataChange::Size,
+                ("content", None) => DataChange::Content,
+                ("other", None) => DataChange::Other,
+                _ => return Err(unknown()),
+            }),
+            ("metadata", sub) => {
+                ModifyKind::Metadata(match split_sub(sub).ok_or_else(unknown)? {
+                    ("any", None) => MetadataKind::Any,
+                    ("access-time", None) => MetadataKind::AccessTime,
+                    ("write-time", None) => MetadataKind::WriteTime,
+                    ("permissions", None) => MetadataKind::Permissions,
+                    ("ownership", None) => MetadataKind::Ownership,
+                    ("extended", None) => MetadataKind::Extended,
+                    ("other", None) => MetadataKind::Other,
+                    _ => return Err(unknown()),
+                })
+            }
+            ("rename", sub) => ModifyKind::Name(match split_sub(sub).ok_or_else(unknown)? {
+                ("any", None) => RenameMode::Any,
+                ("to", None) => RenameMode::To,
+                ("from", None) => RenameMode::From,
+                ("both", None) => RenameMode::Both,
+                ("other", None) => RenameMode::Other,
+                _ => return Err(unknown()),
+            }),
+            ("other", None) => ModifyKind::Other,
+            _ => return Err(unknown()),
+        }),
+        ("remove", sub) => EventKind::Remove(match split_sub(sub).ok_or_else(unknown)? {
+            ("any", None) => RemoveKind::Any,
+            ("file", None) => RemoveKind::File,
+            ("folder", None) => RemoveKind::Folder,
+            ("other", None) => RemoveKind::Other,
+            _ => return Err(unknown()),
+        }),
+        _ => return Err(unknown()),
+    };
+
+    Ok(kind)
+}
+
+fn nested(name: &str, sub: Option<&str>) -> String {
+    match sub {
+        Some(sub) => format!("{}({})", name, sub),
+        None => name.to_string(),
+    }
+}
+
+fn access_mode_to_text(mode: &AccessMode) -> Option<&'static str> {
+    match mode {
+        AccessMode::Any => None,
+        AccessMode::Execute => Some("execute"),
+        AccessMode::Read => Some("read"),
+        AccessMode::Write => Some("write"),
+        AccessMode::Other => Some("other"),
+    }
+}
+
+fn access_mode_from_text(text: Option<&str>) -> Option<AccessMode> {
+    match split_sub(text)? {
+        ("any", None) => Some(AccessMode::Any),
+        ("execute", None) => Some(AccessMode::Execute),
+        ("read", None) => Some(AccessMode::Read),
+        ("write", None) => Some(AccessMode::Write),
+        ("other", None) => Some(AccessMode::Other),
+        _ => None,
+    }
+}
+
+fn flag_to_text(flag: Flag) -> &'static str {
+    match flag {
+        Flag::Rescan => "rescan",
+    }
+}
+
+fn flag_from_text(text: &str) -> Option<Flag> {
+    match text {
+        "rescan" => Some(Flag::Rescan),
+        _ => None,
+    }
+}
+
+/// Splits `name(sub)` into its parts, a missing sub kind is `None`.
+fn split_kind(text: &str) -> Option<(&str, Option<&str>)> {
+    match text.find('(') {
+        Some(open) => {
+            let sub = text[open + 1..].strip_suffix(')')?;
+            Some((&text[..open], Some(sub)))
+        }
+        None => Some((text, None)),
+    }
+}
+
+/// Like `split_kind`, but a missing sub kind is treated as `any`.
+fn split_sub(sub: Option<&str>) -> Option<(&str, Option<&str>)> {
+    split_kind(sub.unwrap_or("any"))
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_os_str().as_bytes().into()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    match path.to_string_lossy() {
+        std::borrow::Cow::Borrowed(s) => s.as_bytes().into(),
+        std::borrow::Cow::Owned(s) => s.into_bytes().into(),
+    }
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    Some(OsString::from_vec(bytes).into())
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+/// Quotes and escapes a string, invalid UTF-8 sequences are escaped as `\xNN`.
+fn quote(mut bytes: &[u8]) -> String {
+    let mut quoted = String::with_capacity(bytes.len() + 2);
+    quoted.push('"');
+
+    while !bytes.is_empty() {
+        let (valid, invalid) = match std::str::from_utf8(bytes) {
+            Ok(valid) => (valid, &[][..]),
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                let invalid_len = e.error_len().unwrap_or(rest.len());
+                // the prefix was just validated
+                (std::str::from_utf8(valid).unwrap(), &rest[..invalid_len])
+            }
+        };
+
+        for c in valid.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if c.is_control() => {
+                    let _ = write!(quoted, "\\u{{{:x}}}", c as u32);
+                }
+                c => quoted.push(c),
+            }
+        }
+        for b in invalid {
+            let _ = write!(quoted, "\\x{:02x}", b);
+        }
+
+        bytes = &bytes[valid.len() + invalid.len()..];
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest().is_empty()
+    }
+
+    fn error(&self, msg: &str) -> Error {
+        Error::generic(&format!("{} at offset {}", msg, self.pos))
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, b: u8) -> bool {
+        if self.rest().as_bytes().first() == Some(&b) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reads until the next whitespace or closing bracket.
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == ']')
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    /// Reads an attribute key including the `=`.
+    fn key(&mut self) -> Result<&'a str> {
+        let rest = self.rest();
+        match rest.find('=') {
+            Some(len) if !rest[..len].contains(char::is_whitespace) => {
+                self.pos += len + 1;
+                Ok(&rest[..len])
+            }
+            _ => Err(self.error("expected attribute")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = self.quoted()?;
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    fn path(&mut self) -> Result<PathBuf> {
+        if self.rest().starts_with('"') {
+            let bytes = self.quoted()?;
+            path_from_bytes(bytes).ok_or_else(|| self.error("invalid path"))
+        } else {
+            let rest = self.rest();
+            let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            self.pos += len;
+            Ok(PathBuf::from(&rest[..len]))
+        }
+    }
+
+    /// Reads a quoted string, returning the unescaped bytes.
+    fn quoted(&mut self) -> Result<Vec<u8>> {
+        if !self.eat(b'"') {
+            return Err(self.error("expected `\"`"));
+        }
+
+        let mut bytes = Vec::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(bytes);
+                }
+                '\\' => {
+                    let escaped = match chars.next() {
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, 'x')) => {
+                            let hex: String = chars.by_ref().take(2).map(|(_, c)| c).collect();
+                            let b = u8::from_str_radix(&hex, 16)
+                                .map_err(|_| self.error("invalid `\\x` escape"))?;
+                            bytes.push(b);
+                            continue;
+                        }
+                        Some((_, 'u')) => {
+                            let hex: String = chars
+                                .by_ref()
+                                .map(|(_, c)| c)
+                                .take_while(|c| *c != '}')
+                                .collect();
+                            hex.strip_prefix('{')
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid `\\u` escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                c => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+        }
+
+        Err(self.error("unterminated string"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_kinds() -> Vec<EventKind> {
+        let modes = [
+            AccessMode::Any,
+            AccessMode::Execute,
+            AccessMode::Read,
+            AccessMode::Write,
+            AccessMode::Other,
+        ];
+
+        let mut kinds = vec![
+            EventKind::Any,
+            EventKind::Other,
+            EventKind::Access(AccessKind::Any),
+            EventKind::Access(AccessKind::Read),
+            EventKind::Access(AccessKind::Other),
+            EventKind::Modify(ModifyKind::Any),
+            EventKind::Modify(ModifyKind::Other),
+        ];
+        for mode in modes {
+            kinds.push(EventKind::Access(AccessKind::Open(mode)));
+            kinds.push(EventKind::Access(AccessKind::Close(mode)));
+        }
+        for kind in [
+            CreateKind::Any,
+            CreateKind::File,
+            CreateKind::Folder,
+            CreateKind::Other,
+        ] {
+            kinds.push(EventKind::Create(kind));
+        }
+        for change in [
+            DataChange::Any,
+            DataChange::Size,
+            DataChange::Content,
+            DataChange::Other,
+        ] {
+            kinds.push(EventKind::Modify(ModifyKind::Data(change)));
+        }
+        for kind in [
+            MetadataKind::Any,
+            MetadataKind::AccessTime,
+            MetadataKind::WriteTime,
+            MetadataKind::Permissions,
+            MetadataKind::Ownership,
+            MetadataKind::Extended,
+            MetadataKind::Other,
+        ] {
+            kinds.push(EventKind::Modify(ModifyKind::Metadata(kind)));
+        }
+        for mode in [
+            RenameMode::Any,
+            RenameMode::To,
+            RenameMode::From,
+            RenameMode::Both,
+            RenameMode::Other,
+        ] {
+            kinds.push(EventKind::Modify(ModifyKind::Name(mode)));
+        }
+        for kind in [
+            RemoveKind::Any,
+            RemoveKind::File,
+            RemoveKind::Folder,
+            RemoveKind::Other,
+        ] {
+            kinds.push(EventKind::Remove(kind));
+        }
+
+        kinds
+    }
+
+    #[test]
+    fn kinds_round_trip() {
+        for kind in all_kinds() {
+            let text = kind_to_text(&kind);
+            assert_eq!(kind_from_text(&text).unwrap(), kind, "{}", text);
+        }
+    }
+
+    #[test]
+    fn kinds_omit_any() {
+        assert_eq!(
+            kind_to_text(&EventKind::Modify(ModifyKind::Data(DataChange::Any))),
+            "modify(data)"
+        );
+        assert_eq!(
+            kind_to_text(&EventKind::Access(AccessKind::Close(AccessMode::Write))),
+            "access(close(write))"
+        );
+        assert_eq!(
+            kind_from_text("modify(data(any))").unwrap(),
+            EventKind::Modify(ModifyKind::Data(DataChange::Any))
+        );
+        assert!(kind_from_text("modify(data").is_err());
+        assert!(kind_from_text("create(file(any))").is_err());
+    }
+
+    #[test]
+    fn events_round_trip() {
+        let events = vec![
+            Event::new(EventKind::Create(CreateKind::File)).add_path("/watch/file".into()),
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                .set_tracker(42)
+                .add_path("/watch/from".into())
+                .add_path("/watch/to".into()),
+            Event::new(EventKind::Other).set_flag(Flag::Rescan),
+            Event::new(EventKind::Remove(RemoveKind::Any))
+                .set_info("override \"quoted\"")
+                .set_source("inotify")
+                .add_path("/watch/with space".into())
+                .add_path("[bracket".into())
+                .add_path("".into())
+                .add_path("tab\there\\".into())
+                .add_path("/watch/ünïcödé".into()),
+        ];
+
+        for event in events {
+            let line = to_text(&event);
+            assert!(!line.contains('\n'), "{}", line);
+            assert_eq!(from_text(&line).unwrap(), event, "{}", line);
+        }
+    }
+
+    #[test]
+    fn text_form() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .set_tracker(7)
+            .add_path("/a b".into())
+            .add_path("/c".into());
+
+        assert_eq!(
+            to_text(&event),
+            r#"modify(rename(both)) [tracker=7] "/a b" /c"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_round_trip() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = PathBuf::from(OsStr::from_bytes(b"/watch/\xff\xfeinvalid"));
+        let event = Event::new(EventKind::Create(CreateKind::Any)).add_path(path);
+
+        let line = to_text(&event);
+        assert_eq!(line, r#"create "/watch/\xff\xfeinvalid""#);
+        assert_eq!(from_text(&line).unwrap(), event);
+    }
+
+    #[test]
+    fn invalid_text() {
+        assert!(from_text("").is_err());
+        assert!(from_text("bogus /path").is_err());
+        assert!(from_text("create [tracker=abc] /path").is_err());
+        assert!(from_text("create [unknown=1] /path").is_err());
+        assert!(from_text("create \"/unterminated").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_lines_round_trip() {
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .set_tracker(42)
+            .set_info("info")
+            .add_path("/watch/from".into())
+            .add_path("/watch/to".into());
+
+        let line = to_json_line(&event).unwrap();
+        assert!(!line.contains('\n'));
+        assert_eq!(from_json_line(&line).unwrap(), event);
+        assert_eq!(
+            Format::Json
+                .decode(&Format::Json.encode(&event).unwrap())
+                .unwrap(),
+            event
+        );
+    }
+
+    #[test]
+    fn quoted_strings() {
+        for s in ["", "plain", "with \"quotes\" and \\", "line\nbreak\u{7}"] {
+            assert_eq!(unquote_str(&quote_str(s)).unwrap(), s);
+        }
+        assert!(unquote_str("unquoted").is_err());
+        assert!(unquote_str("\"a\" b").is_err());
+        assert!(unquote_str("\"unterminated").is_err());
+    }
+}
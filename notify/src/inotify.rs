@@ -6,6 +6,8 @@
 
 use super::event::*;
 use super::{Config, Error, ErrorKind, EventHandler, RecursiveMode, Result, Watcher};
+use crate::coalesce::EventBatch;
+use crate::rename::RenameTable;
 use crate::{bounded, unbounded, BoundSender, Receiver, Sender};
 use inotify as inotify_sys;
 use inotify_sys::{EventMask, Inotify, WatchDescriptor, WatchMask};
@@ -17,6 +19,7 @@ use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
+use std::time::Instant;
 use walkdir::WalkDir;
 
 const INOTIFY: mio::Token = mio::Token(0);
@@ -39,7 +42,8 @@ struct EventLoop {
     /// PathBuf -> (WatchDescriptor, WatchMask, is_recursive, is_dir)
     watches: HashMap<PathBuf, (WatchDescriptor, WatchMask, bool, bool)>,
     paths: HashMap<WatchDescriptor, PathBuf>,
-    rename_event: Option<Event>,
+    renames: RenameTable,
+    event_coalescing: bool,
 }
 
 /// Watcher implementation based on inotify
@@ -90,7 +94,11 @@ fn remove_watch_by_event(
 }
 
 impl EventLoop {
-    pub fn new(inotify: Inotify, event_handler: Box<dyn EventHandler>) -> Result<Self> {
+    pub fn new(
+        inotify: Inotify,
+        event_handler: Box<dyn EventHandler>,
+        config: Config,
+    ) -> Result<Self> {
         let (event_loop_tx, event_loop_rx) = unbounded::<EventLoopMsg>();
         let poll = mio::Poll::new()?;
 
@@ -111,7 +119,8 @@ impl EventLoop {
             event_handler,
             watches: HashMap::new(),
             paths: HashMap::new(),
-            rename_event: None,
+            renames: RenameTable::new(config.rename_timeout()),
+            event_coalescing: config.event_coalescing(),
         };
         Ok(event_loop)
     }
@@ -126,8 +135,12 @@ impl EventLoop {
     fn event_loop_thread(mut self) {
         let mut events = mio::Events::with_capacity(16);
         loop {
-            // Wait for something to happen.
-            match self.poll.poll(&mut events, None) {
+            // Wait for something to happen or for the next pending rename to expire.
+            let timeout = self
+                .renames
+                .next_expiry()
+                .map(|expiry| expiry.saturating_duration_since(Instant::now()));
+            match self.poll.poll(&mut events, timeout) {
                 Err(ref e) if matches!(e.kind(), std::io::ErrorKind::Interrupted) => {
                     // System call was interrupted, we will retry
                     // TODO: Not covered by tests (to reproduce likely need to setup signal handlers)
@@ -141,6 +154,11 @@ impl EventLoop {
                 self.handle_event(event);
             }
 
+            // Renames without a matching `To` event were moves out of the watched tree.
+            for event in self.renames.expire(Instant::now()) {
+                self.event_handler.handle_event(Ok(event));
+            }
+
             // Stop, if we're done.
             if !self.running {
                 break;
@@ -195,6 +213,7 @@ impl EventLoop {
     fn handle_inotify(&mut self) {
         let mut add_watches = Vec::new();
         let mut remove_watches = Vec::new();
+        let mut batch = EventBatch::new(self.event_coalescing);
 
         if let Some(ref mut inotify) = self.inotify {
             let mut buffer = [0; 1024];
@@ -209,7 +228,7 @@ impl EventLoop {
                             num_events += 1;
                             if event.mask.contains(EventMask::Q_OVERFLOW) {
                                 let ev = Ok(Event::new(EventKind::Other).set_flag(Flag::Rescan));
-                                self.event_handler.handle_event(ev);
+                                batch.push(ev, &mut *self.event_handler);
                             }
 
                             let path = match event.name {
@@ -220,6 +239,7 @@ impl EventLoop {
                             let mut evs = Vec::new();
 
                             if event.mask.contains(EventMask::MOVED_FROM) {
+                                let is_dir = event.mask.contains(EventMask::ISDIR);
                                 remove_watch_by_event(&path, &self.watches, &mut remove_watches);
 
                                 let event = Event::new(EventKind::Modify(ModifyKind::Name(
@@ -228,7 +248,7 @@ impl EventLoop {
                                 .add_some_path(path.clone())
                                 .set_tracker(event.cookie as usize);
 
-                                self.rename_event = Some(event.clone());
+                                self.renames.add_from(event.clone(), is_dir, Instant::now());
 
                                 evs.push(event);
                             } else if event.mask.contains(EventMask::MOVED_TO) {
@@ -238,16 +258,9 @@ impl EventLoop {
                                         .add_some_path(path.clone()),
                                 );
 
-                                let trackers_match = self
-                                    .rename_event
-                                    .as_ref()
-                                    .and_then(|e| e.tracker())
-                                    .map_or(false, |from_tracker| {
-                                        from_tracker == event.cookie as usize
-                                    });
-
-                                if trackers_match {
-                                    let rename_event = self.rename_event.take().unwrap(); // unwrap is safe because `rename_event` must be set at this point
+                                if let Some(rename_event) =
+                                    self.renames.take_from(event.cookie as usize)
+                                {
                                     evs.push(
                                         Event::new(EventKind::Modify(ModifyKind::Name(
                                             RenameMode::Both,
@@ -359,7 +372,7 @@ impl EventLoop {
                             }
 
                             for ev in evs {
-                                self.event_handler.handle_event(Ok(ev));
+                                batch.push(Ok(ev), &mut *self.event_handler);
                             }
                         }
 
@@ -369,12 +382,14 @@ impl EventLoop {
                         }
                     }
                     Err(e) => {
-                        self.event_handler.handle_event(Err(Error::io(e)));
+                        batch.push(Err(Error::io(e)), &mut *self.event_handler);
                     }
                 }
             }
         }
 
+        batch.flush(&mut *self.event_handler);
+
         for path in remove_watches {
             self.remove_watch(path, true).ok();
         }
@@ -514,9 +529,9 @@ fn filter_dir(e: walkdir::Result<walkdir::DirEntry>) -> Option<walkdir::DirEntry
 }
 
 impl INotifyWatcher {
-    fn from_event_handler(event_handler: Box<dyn EventHandler>) -> Result<Self> {
+    fn from_event_handler(event_handler: Box<dyn EventHandler>, config: Config) -> Result<Self> {
         let inotify = Inotify::init()?;
-        let event_loop = EventLoop::new(inotify, event_handler)?;
+        let event_loop = EventLoop::new(inotify, event_handler, config)?;
         let channel = event_loop.event_loop_tx.clone();
         let waker = event_loop.event_loop_waker.clone();
         event_loop.run();
@@ -558,8 +573,8 @@ impl INotifyWatcher {
 
 impl Watcher for INotifyWatcher {
     /// Create a new watcher.
-    fn new<F: EventHandler>(event_handler: F, _config: Config) -> Result<Self> {
-        Self::from_event_handler(Box::new(event_handler))
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        Self::from_event_handler(Box::new(event_handler), config)
     }
 
     fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
@@ -595,3 +610,116 @@ fn inotify_watcher_is_send_and_sync() {
     fn check<T: Send + Sync>() {}
     check::<INotifyWatcher>();
 }
+
+/// Collect the events of `rx` until `done` returns true or a few seconds passed
+#[cfg(test)]
+fn collect_events(
+    rx: &std::sync::mpsc::Receiver<Result<Event>>,
+    done: impl Fn(&[Event]) -> bool,
+) -> Vec<Event> {
+    let deadline = Instant::now() + std::time::Duration::from_secs(5);
+    let mut events = Vec::new();
+    while !done(&events) {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(timeout) {
+            Ok(event) => events.push(event.unwrap()),
+            Err(_) => break,
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+fn is_rename(event: &Event, from: &Path, to: &Path) -> bool {
+    event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::Both)) && event.paths == [from, to]
+}
+
+#[test]
+fn inotify_pairs_concurrent_renames() {
+    use std::fs;
+
+    let dir = tempfile::tempdir().unwrap();
+    let watched = dir.path().join("watched");
+    let outside = dir.path().join("outside");
+    fs::create_dir(&watched).unwrap();
+    fs::create_dir(&outside).unwrap();
+    fs::write(watched.join("moved-out"), "").unwrap();
+    fs::write(watched.join("a"), "").unwrap();
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_rename_timeout(std::time::Duration::from_millis(100));
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher.watch(&watched, RecursiveMode::Recursive).unwrap();
+
+    // the move out stays unmatched while the second rename happens
+    fs::rename(watched.join("moved-out"), outside.join("moved-out")).unwrap();
+    fs::rename(watched.join("a"), watched.join("b")).unwrap();
+
+    let removed = |event: &Event| {
+        event.kind == EventKind::Remove(RemoveKind::File)
+            && event.paths == [watched.join("moved-out")]
+    };
+    let events = collect_events(&rx, |events| events.iter().any(removed));
+
+    assert!(
+        events
+            .iter()
+            .any(|event| is_rename(event, &watched.join("a"), &watched.join("b"))),
+        "{events:?}"
+    );
+    assert!(events.iter().any(removed), "{events:?}");
+    assert_eq!(
+        events
+            .iter()
+            .filter(|event| event.kind == EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .count(),
+        1,
+        "{events:?}"
+    );
+}
+
+#[test]
+fn inotify_pairs_renames_across_reads() {
+    use std::fs;
+
+    // Each event with such a name takes 192 bytes, so only five of them fit into the read buffer
+    // and a `MOVED_FROM` and `MOVED_TO` pair can be split across two reads.
+    let name = |i: usize| format!("{i:02}{}", "x".repeat(168));
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..20 {
+        fs::write(dir.path().join(name(i)), "").unwrap();
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let config = Config::default().with_rename_timeout(std::time::Duration::from_millis(100));
+    let mut watcher = INotifyWatcher::new(tx, config).unwrap();
+    watcher
+        .watch(dir.path(), RecursiveMode::NonRecursive)
+        .unwrap();
+
+    for i in 0..20 {
+        fs::rename(dir.path().join(name(i)), dir.path().join(name(i + 20))).unwrap();
+    }
+
+    let is_renamed = |events: &[Event], i: usize| {
+        events.iter().any(|event| {
+            is_rename(
+                event,
+                &dir.path().join(name(i)),
+                &dir.path().join(name(i + 20)),
+            )
+        })
+    };
+    let events = collect_events(&rx, |events| (0..20).all(|i| is_renamed(events, i)));
+    assert!((0..20).all(|i| is_renamed(&events, i)), "{events:?}");
+
+    // all moves were matched, so none of them expires into a remove event
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    let late: Vec<_> = rx.try_iter().collect::<Result<_>>().unwrap();
+    assert!(
+        !late
+            .iter()
+            .any(|event| matches!(event.kind, EventKind::Remove(_))),
+        "{late:?}"
+    );
+}
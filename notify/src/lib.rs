@@ -18,6 +18,7 @@
 //! - `macos_fsevent` enabled by default, for fsevent backend on macos
 //! - `macos_kqueue` for kqueue backend on macos
 //! - `crossbeam-channel` enabled by default, see below
+//! - `memory` for the in-memory file system and `MemoryWatcher`, for deterministic tests
 //!
 //! ### Serde
 //!
@@ -27,6 +28,8 @@
 //! notify = { version = "6.1.1", features = ["serde"] }
 //! ```
 //!
+//! This also enables the JSON Lines encoding in [`event::format`].
+//!
 //! ### Crossbeam-Channel & Tokio
 //!
 //! By default crossbeam-channel is used internally by notify. Which also allows the [Watcher] to be sync.
@@ -223,8 +226,12 @@ pub use crate::inotify::INotifyWatcher;
     all(target_os = "macos", feature = "macos_kqueue")
 ))]
 pub use crate::kqueue::KqueueWatcher;
+#[cfg(any(test, feature = "memory"))]
+pub use memory::MemoryWatcher;
+pub use mock::MockWatcher;
 pub use null::NullWatcher;
 pub use poll::PollWatcher;
+pub use replay::ReplayWatcher;
 #[cfg(target_os = "windows")]
 pub use windows::ReadDirectoryChangesWatcher;
 
@@ -245,11 +252,17 @@ pub mod kqueue;
 pub mod windows;
 
 pub mod event;
+#[cfg(any(test, feature = "memory"))]
+pub mod memory;
+pub mod mock;
 pub mod null;
 pub mod poll;
+pub mod replay;
 
+mod coalesce;
 mod config;
 mod error;
+mod rename;
 
 /// The set of requirements for watcher event handling functions.
 ///
@@ -312,6 +325,12 @@ pub enum WatcherKind {
     ReadDirectoryChangesWatcher,
     /// Fake watcher for testing
     NullWatcher,
+    /// Replays recorded events, for testing
+    ReplayWatcher,
+    /// Scriptable watcher for unit tests
+    MockWatcher,
+    /// In-memory file system, for deterministic tests
+    MemoryWatcher,
 }
 
 /// Type that can deliver file activity notifications
@@ -368,6 +387,30 @@ pub trait Watcher {
         Self: Sized;
 }
 
+/// The watch interface of a debouncer, like [`Watcher`] for the watchers.
+///
+/// It's implemented by the debouncers in `notify-debouncer-full` and `notify-debouncer-mini`, so
+/// code can be written for any debouncer, e.g. with different watcher backends or caches.
+///
+/// ```rust
+/// # use std::path::Path;
+/// use notify::{DebouncedWatcher, RecursiveMode, Result};
+///
+/// fn watch_all(debouncer: &mut impl DebouncedWatcher, paths: &[&Path]) -> Result<()> {
+///     for path in paths {
+///         debouncer.watch(path, RecursiveMode::Recursive)?;
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait DebouncedWatcher {
+    /// Begin watching a new path, see [`Watcher::watch`].
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()>;
+
+    /// Stop watching a path, see [`Watcher::unwatch`].
+    fn unwatch(&mut self, path: &Path) -> Result<()>;
+}
+
 /// The recommended `Watcher` implementation for the current platform
 #[cfg(any(target_os = "linux", target_os = "android"))]
 pub type RecommendedWatcher = INotifyWatcher;
@@ -446,10 +489,13 @@ mod tests {
         assert_debug_impl!(event::RenameMode);
         assert_debug_impl!(Event);
         assert_debug_impl!(EventKind);
+        assert_debug_impl!(MemoryWatcher);
+        assert_debug_impl!(MockWatcher);
         assert_debug_impl!(NullWatcher);
         assert_debug_impl!(PollWatcher);
         assert_debug_impl!(RecommendedWatcher);
         assert_debug_impl!(RecursiveMode);
+        assert_debug_impl!(ReplayWatcher);
         assert_debug_impl!(WatcherKind);
     }
 }
@@ -0,0 +1,1157 @@
+//! In-memory file system and `Watcher` implementation for deterministic tests
+//!
+//! [`MemoryFs`] is a minimal model of a file system. Every operation on it immediately produces
+//! the events the [inotify](crate::inotify) backend would emit for the same operation on a real
+//! disk, including rename trackers and `Access(Close(Write))` events. The events are delivered to
+//! all [`MemoryWatcher`]s attached to the file system, on the thread calling the operation.
+//! Event handlers may read the file system, but must not change it, which would deadlock.
+//!
+//! The translation of the raw inotify events mirrors `INotifyWatcher`, including the way watches
+//! are added for new directories inside a recursive watch. Identical consecutive raw events of a
+//! single operation are merged, like the kernel does for unread events. Like with inotify, events
+//! inside a new directory are missed until the next operation, e.g. for the nested directories
+//! created by [`MemoryFs::create_dir_all`].
+//!
+//! Renames are complete at the end of an operation. A rename out of the watched tree results in a
+//! `Remove` event right away instead of after the rename timeout of the inotify backend.
+//!
+//! ```
+//! # use std::path::Path;
+//! use notify::{memory::MemoryFs, Config, MemoryWatcher, RecursiveMode, Watcher};
+//!
+//! # fn main() -> notify::Result<()> {
+//! let fs = MemoryFs::new();
+//! fs.create_dir_all("/watch")?;
+//!
+//! let (tx, rx) = std::sync::mpsc::channel();
+//! let mut watcher = MemoryWatcher::with_fs(&fs, tx);
+//! watcher.watch(Path::new("/watch"), RecursiveMode::Recursive)?;
+//!
+//! fs.write("/watch/file", b"content")?;
+//! fs.rename("/watch/file", "/watch/renamed")?;
+//! drop(watcher);
+//!
+//! for event in rx {
+//!     println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::coalesce::EventBatch;
+use crate::event::*;
+use crate::rename::RenameTable;
+use crate::{Config, Error, EventHandler, RecursiveMode, Result, Watcher};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+// raw inotify event masks, see inotify(7)
+const MODIFY: u32 = 0x0000_0002;
+const ATTRIB: u32 = 0x0000_0004;
+const CLOSE_WRITE: u32 = 0x0000_0008;
+const MOVED_FROM: u32 = 0x0000_0040;
+const MOVED_TO: u32 = 0x0000_0080;
+const CREATE: u32 = 0x0000_0100;
+const DELETE: u32 = 0x0000_0200;
+const DELETE_SELF: u32 = 0x0000_0400;
+const MOVE_SELF: u32 = 0x0000_0800;
+const Q_OVERFLOW: u32 = 0x0000_4000;
+const ISDIR: u32 = 0x4000_0000;
+
+/// Events reported to the watch of the parent directory, with the name of the child.
+const PARENT_EVENTS: u32 = MODIFY | ATTRIB | CLOSE_WRITE | MOVED_FROM | MOVED_TO | CREATE | DELETE;
+/// Events reported to the watch of the file or directory itself.
+const SELF_EVENTS: u32 = MODIFY | ATTRIB | CLOSE_WRITE | DELETE_SELF | MOVE_SELF;
+
+/// Same as the watch mask used by the inotify backend.
+const WATCH_MASK: u32 = ATTRIB | CREATE | DELETE | CLOSE_WRITE | MODIFY | MOVED_FROM | MOVED_TO;
+const WATCH_SELF_MASK: u32 = DELETE_SELF | MOVE_SELF;
+
+/// A raw inotify event, before it is routed to the watches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RawEvent {
+    path: PathBuf,
+    mask: u32,
+    cookie: u32,
+    /// Only report to the watch of the path itself, e.g. link count changes.
+    self_only: bool,
+}
+
+#[derive(Debug)]
+enum Node {
+    File { ino: u64, data: Vec<u8> },
+    Dir { ino: u64 },
+}
+
+impl Node {
+    fn ino(&self) -> u64 {
+        match self {
+            Node::File { ino, .. } | Node::Dir { ino } => *ino,
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self, Node::Dir { .. })
+    }
+}
+
+#[derive(Debug, Default)]
+struct FsState {
+    nodes: BTreeMap<PathBuf, Node>,
+    next_ino: u64,
+    next_cookie: u32,
+    watchers: Vec<Weak<WatcherInner>>,
+}
+
+impl FsState {
+    fn new_ino(&mut self) -> u64 {
+        self.next_ino += 1;
+        self.next_ino
+    }
+
+    fn new_cookie(&mut self) -> u32 {
+        self.next_cookie += 1;
+        self.next_cookie
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        is_root(path) || self.nodes.get(path).map_or(false, Node::is_dir)
+    }
+
+    fn check_parent(&self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if self.is_dir(parent) => Ok(()),
+            _ => Err(not_found(path)),
+        }
+    }
+
+    /// Paths below `path`, deepest first.
+    fn descendants(&self, path: &Path) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = self
+            .nodes
+            .keys()
+            .filter(|p| p.starts_with(path) && *p != path)
+            .cloned()
+            .collect();
+        paths.sort_by_key(|p| std::cmp::Reverse(p.components().count()));
+        paths
+    }
+}
+
+/// In-memory file system model
+///
+/// Cloning a `MemoryFs` returns a handle to the same file system. Paths are used as given, there
+/// is no current directory and no symbolic links. Root paths like `/` always exist.
+///
+/// See the [module documentation](self) for an example.
+#[derive(Clone, Default)]
+pub struct MemoryFs {
+    state: Arc<Mutex<FsState>>,
+}
+
+impl MemoryFs {
+    /// Create an empty file system.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Create a new directory, like [`std::fs::create_dir`].
+    pub fn create_dir(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        self.change(|state, raw| {
+            state.check_parent(path)?;
+            if state.nodes.contains_key(path) || is_root(path) {
+                return Err(already_exists(path));
+            }
+            let ino = state.new_ino();
+            state.nodes.insert(path.to_path_buf(), Node::Dir { ino });
+            push(raw, path, CREATE | ISDIR, 0);
+            Ok(())
+        })
+    }
+
+    /// Create a directory and all missing parents, like [`std::fs::create_dir_all`].
+    pub fn create_dir_all(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        self.change(|state, raw| {
+            let mut missing = Vec::new();
+            for ancestor in path.ancestors() {
+                if state.is_dir(ancestor) {
+                    break;
+                }
+                if state.nodes.contains_key(ancestor) {
+                    return Err(already_exists(ancestor));
+                }
+                missing.push(ancestor);
+            }
+            for dir in missing.into_iter().rev() {
+                let ino = state.new_ino();
+                state.nodes.insert(dir.to_path_buf(), Node::Dir { ino });
+                push(raw, dir, CREATE | ISDIR, 0);
+            }
+            Ok(())
+        })
+    }
+
+    /// Create an empty file or truncate an existing one, like [`std::fs::File::create`].
+    pub fn create_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write(path, [])
+    }
+
+    /// Replace the content of a file, creating it if necessary, like [`std::fs::write`].
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let (path, contents) = (path.as_ref(), contents.as_ref());
+        self.change(|state, raw| {
+            state.check_parent(path)?;
+            match state.nodes.get_mut(path) {
+                Some(Node::File { data, .. }) => {
+                    if !data.is_empty() {
+                        // truncate
+                        push(raw, path, MODIFY, 0);
+                    }
+                    *data = contents.to_vec();
+                }
+                Some(Node::Dir { .. }) => return Err(is_a_directory(path)),
+                None => {
+                    let ino = state.new_ino();
+                    state.nodes.insert(
+                        path.to_path_buf(),
+                        Node::File {
+                            ino,
+                            data: contents.to_vec(),
+                        },
+                    );
+                    push(raw, path, CREATE, 0);
+                }
+            }
+            if !contents.is_empty() {
+                push(raw, path, MODIFY, 0);
+            }
+            push(raw, path, CLOSE_WRITE, 0);
+            Ok(())
+        })
+    }
+
+    /// Append to an existing file.
+    pub fn append(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let (path, contents) = (path.as_ref(), contents.as_ref());
+        self.change(|state, raw| match state.nodes.get_mut(path) {
+            Some(Node::File { data, .. }) => {
+                data.extend_from_slice(contents);
+                if !contents.is_empty() {
+                    push(raw, path, MODIFY, 0);
+                }
+                push(raw, path, CLOSE_WRITE, 0);
+                Ok(())
+            }
+            Some(Node::Dir { .. }) => Err(is_a_directory(path)),
+            None => Err(not_found(path)),
+        })
+    }
+
+    /// Change the metadata of an existing file or directory, e.g. its permissions.
+    pub fn touch(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        self.change(|state, raw| match state.nodes.get(path) {
+            Some(node) => {
+                let isdir = if node.is_dir() { ISDIR } else { 0 };
+                push(raw, path, ATTRIB | isdir, 0);
+                Ok(())
+            }
+            None => Err(not_found(path)),
+        })
+    }
+
+    /// Rename a file or directory, like [`std::fs::rename`].
+    ///
+    /// An existing file at `to` is replaced, if `from` is a file as well. Renaming a path to
+    /// itself does nothing.
+    pub fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.change(|state, raw| {
+            let is_dir = match state.nodes.get(from) {
+                Some(node) => node.is_dir(),
+                None => return Err(not_found(from)),
+            };
+            if from == to {
+                return Ok(());
+            }
+            state.check_parent(to)?;
+            match state.nodes.get(to) {
+                Some(Node::File { .. }) if !is_dir => {}
+                Some(_) => return Err(already_exists(to)),
+                None if is_root(to) => return Err(already_exists(to)),
+                None => {}
+            }
+            if to.starts_with(from) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("cannot move {:?} into itself", from),
+                ));
+            }
+
+            let mut moved = state.descendants(from);
+            moved.push(from.to_path_buf());
+            for old in moved {
+                let node = state.nodes.remove(&old).unwrap(); // unwrap is safe because the paths were just collected
+                let new = to.join(old.strip_prefix(from).unwrap()); // unwrap is safe because `old` starts with `from`
+                state.nodes.insert(new, node);
+            }
+
+            let isdir = if is_dir { ISDIR } else { 0 };
+            let cookie = state.new_cookie();
+            push(raw, from, MOVED_FROM | isdir, cookie);
+            push(raw, to, MOVED_TO | isdir, cookie);
+            push_self(raw, from, MOVE_SELF | isdir);
+            Ok(())
+        })
+    }
+
+    /// Remove a file, like [`std::fs::remove_file`].
+    pub fn remove_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        self.change(|state, raw| match state.nodes.get(path) {
+            Some(Node::File { .. }) => {
+                state.nodes.remove(path);
+                push_remove(raw, path, false);
+                Ok(())
+            }
+            Some(Node::Dir { .. }) => Err(is_a_directory(path)),
+            None => Err(not_found(path)),
+        })
+    }
+
+    /// Remove an empty directory, like [`std::fs::remove_dir`].
+    pub fn remove_dir(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        self.change(|state, raw| match state.nodes.get(path) {
+            Some(Node::Dir { .. }) => {
+                if !state.descendants(path).is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        format!("directory not empty: {:?}", path),
+                    ));
+                }
+                state.nodes.remove(path);
+                push_remove(raw, path, true);
+                Ok(())
+            }
+            Some(Node::File { .. }) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("not a directory: {:?}", path),
+            )),
+            None => Err(not_found(path)),
+        })
+    }
+
+    /// Remove a directory and all of its content, like [`std::fs::remove_dir_all`].
+    pub fn remove_dir_all(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        self.change(|state, raw| {
+            if !state.nodes.get(path).map_or(false, Node::is_dir) {
+                return Err(not_found(path));
+            }
+            let mut removed = state.descendants(path);
+            removed.push(path.to_path_buf());
+            for p in removed {
+                let node = state.nodes.remove(&p).unwrap(); // unwrap is safe because the paths were just collected
+                push_remove(raw, &p, node.is_dir());
+            }
+            Ok(())
+        })
+    }
+
+    /// Simulate an overflow of the event queue, which results in a rescan event.
+    pub fn overflow(&self) {
+        let _ = self.change(|_, raw| {
+            push(raw, Path::new(""), Q_OVERFLOW, 0);
+            Ok(())
+        });
+    }
+
+    /// Returns whether a file or directory exists at `path`.
+    pub fn exists(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        is_root(path) || self.state.lock().unwrap().nodes.contains_key(path)
+    }
+
+    /// Returns whether `path` is an existing directory.
+    pub fn is_dir(&self, path: impl AsRef<Path>) -> bool {
+        self.state.lock().unwrap().is_dir(path.as_ref())
+    }
+
+    /// Returns the content of a file, like [`std::fs::read`].
+    pub fn read(&self, path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+        let path = path.as_ref();
+        match self.state.lock().unwrap().nodes.get(path) {
+            Some(Node::File { data, .. }) => Ok(data.clone()),
+            Some(Node::Dir { .. }) => Err(is_a_directory(path)),
+            None => Err(not_found(path)),
+        }
+    }
+
+    /// Returns the paths of the direct children of a directory, in lexical order.
+    pub fn read_dir(&self, path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let state = self.state.lock().unwrap();
+        if !state.is_dir(path) {
+            return Err(not_found(path));
+        }
+        Ok(state
+            .nodes
+            .keys()
+            .filter(|p| p.parent() == Some(path))
+            .cloned()
+            .collect())
+    }
+
+    /// Returns the inode number of a file or directory.
+    ///
+    /// Inode numbers are unique within a `MemoryFs` and never reused. They are kept on rename.
+    pub fn inode(&self, path: impl AsRef<Path>) -> Option<u64> {
+        self.state
+            .lock()
+            .unwrap()
+            .nodes
+            .get(path.as_ref())
+            .map(Node::ino)
+    }
+
+    /// Apply a change and deliver the resulting events to all attached watchers.
+    fn change<F>(&self, f: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut FsState, &mut Vec<RawEvent>) -> io::Result<()>,
+    {
+        let mut raw = Vec::new();
+        let watchers = {
+            let mut state = self.state.lock().unwrap();
+            f(&mut state, &mut raw)?;
+            state.watchers.retain(|w| w.strong_count() > 0);
+            state
+                .watchers
+                .iter()
+                .filter_map(Weak::upgrade)
+                .collect::<Vec<_>>()
+        };
+
+        // the lock is released, so event handlers may read the file system. Changing it from an
+        // event handler deadlocks though, as the handler of each watcher is locked while it runs.
+        for watcher in watchers {
+            watcher.handle_raw_events(&raw, self);
+        }
+
+        Ok(())
+    }
+
+    fn attach(&self, watcher: &Arc<WatcherInner>) {
+        self.state
+            .lock()
+            .unwrap()
+            .watchers
+            .push(Arc::downgrade(watcher));
+    }
+
+    fn detach(&self, watcher: &Arc<WatcherInner>) {
+        let watcher = Arc::downgrade(watcher);
+        self.state
+            .lock()
+            .unwrap()
+            .watchers
+            .retain(|w| !w.ptr_eq(&watcher));
+    }
+}
+
+impl fmt::Debug for MemoryFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("MemoryFs")
+            .field("nodes", &state.nodes)
+            .finish()
+    }
+}
+
+fn is_root(path: &Path) -> bool {
+    path.parent().is_none() || path.as_os_str().is_empty()
+}
+
+fn push(raw: &mut Vec<RawEvent>, path: &Path, mask: u32, cookie: u32) {
+    push_raw(raw, path, mask, cookie, false)
+}
+
+fn push_self(raw: &mut Vec<RawEvent>, path: &Path, mask: u32) {
+    push_raw(raw, path, mask, 0, true)
+}
+
+fn push_raw(raw: &mut Vec<RawEvent>, path: &Path, mask: u32, cookie: u32, self_only: bool) {
+    let event = RawEvent {
+        path: path.to_path_buf(),
+        mask,
+        cookie,
+        self_only,
+    };
+    // the kernel merges identical unread events
+    if raw.last() != Some(&event) {
+        raw.push(event);
+    }
+}
+
+fn push_remove(raw: &mut Vec<RawEvent>, path: &Path, is_dir: bool) {
+    if is_dir {
+        push_self(raw, path, DELETE_SELF | ISDIR);
+        push(raw, path, DELETE | ISDIR, 0);
+    } else {
+        // the link count changes
+        push_self(raw, path, ATTRIB);
+        push_self(raw, path, DELETE_SELF);
+        push(raw, path, DELETE, 0);
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("not found: {:?}", path))
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("already exists: {:?}", path),
+    )
+}
+
+fn is_a_directory(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("is a directory: {:?}", path))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Watch {
+    mask: u32,
+    is_recursive: bool,
+    is_dir: bool,
+}
+
+#[derive(Debug)]
+struct WatchState {
+    watches: HashMap<PathBuf, Watch>,
+    renames: RenameTable,
+}
+
+impl Default for WatchState {
+    fn default() -> Self {
+        Self {
+            watches: HashMap::new(),
+            // there is no reordering, a rename is complete at the end of the operation
+            renames: RenameTable::new(Duration::ZERO),
+        }
+    }
+}
+
+struct WatcherInner {
+    state: Mutex<WatchState>,
+    event_handler: Mutex<Box<dyn EventHandler>>,
+    event_coalescing: bool,
+}
+
+impl WatcherInner {
+    fn handle_raw_events(&self, raw: &[RawEvent], fs: &MemoryFs) {
+        let events = self.state.lock().unwrap().translate(raw, fs);
+
+        // one operation is one batch
+        let mut event_handler = self.event_handler.lock().unwrap();
+        let mut batch = EventBatch::new(self.event_coalescing);
+        for event in events {
+            batch.push(Ok(event), &mut **event_handler);
+        }
+        batch.flush(&mut **event_handler);
+    }
+}
+
+impl WatchState {
+    /// Route raw events to the watches and translate them like `INotifyWatcher` does.
+    fn translate(&mut self, raw: &[RawEvent], fs: &MemoryFs) -> Vec<Event> {
+        let mut add_watches = Vec::new();
+        let mut remove_watches = Vec::new();
+        let mut evs = Vec::new();
+
+        for raw_event in raw {
+            if raw_event.mask & Q_OVERFLOW != 0 {
+                evs.push(Event::new(EventKind::Other).set_flag(Flag::Rescan));
+                continue;
+            }
+
+            let mut deliveries = Vec::new();
+            if !raw_event.self_only {
+                if let Some(watch) = raw_event.path.parent().and_then(|p| self.watches.get(p)) {
+                    if raw_event.mask & PARENT_EVENTS & watch.mask != 0 {
+                        deliveries.push(raw_event.path.clone());
+                    }
+                }
+            }
+            if let Some(watch) = self.watches.get(&raw_event.path) {
+                if raw_event.mask & SELF_EVENTS & watch.mask != 0 {
+                    deliveries.push(raw_event.path.clone());
+                }
+            }
+
+            for path in deliveries {
+                self.translate_one(
+                    Some(path),
+                    raw_event,
+                    &mut evs,
+                    &mut add_watches,
+                    &mut remove_watches,
+                );
+            }
+        }
+
+        // renames without a matching `To` event were moves out of the watched tree
+        evs.extend(self.renames.expire_all());
+
+        for path in remove_watches {
+            self.remove_watch(&path, true).ok();
+        }
+
+        for path in add_watches {
+            self.add_watch(fs, path, true, false).ok();
+        }
+
+        evs
+    }
+
+    fn translate_one(
+        &mut self,
+        path: Option<PathBuf>,
+        raw_event: &RawEvent,
+        evs: &mut Vec<Event>,
+        add_watches: &mut Vec<PathBuf>,
+        remove_watches: &mut Vec<PathBuf>,
+    ) {
+        let mask = raw_event.mask;
+        let cookie = raw_event.cookie as usize;
+
+        if mask & MOVED_FROM != 0 {
+            self.remove_watch_by_event(&path, remove_watches);
+
+            let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+                .add_some_path(path.clone())
+                .set_tracker(cookie);
+
+            self.renames
+                .add_from(event.clone(), mask & ISDIR != 0, Instant::now());
+
+            evs.push(event);
+        } else if mask & MOVED_TO != 0 {
+            evs.push(
+                Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::To)))
+                    .set_tracker(cookie)
+                    .add_some_path(path.clone()),
+            );
+
+            if let Some(rename_event) = self.renames.take_from(cookie) {
+                evs.push(
+                    Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                        .set_tracker(cookie)
+                        .add_some_path(rename_event.paths.first().cloned())
+                        .add_some_path(path.clone()),
+                );
+            }
+            self.add_watch_by_event(&path, mask, add_watches);
+        }
+        if mask & MOVE_SELF != 0 {
+            evs.push(
+                Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+                    .add_some_path(path.clone()),
+            );
+        }
+        if mask & CREATE != 0 {
+            evs.push(
+                Event::new(EventKind::Create(if mask & ISDIR != 0 {
+                    CreateKind::Folder
+                } else {
+                    CreateKind::File
+                }))
+                .add_some_path(path.clone()),
+            );
+            self.add_watch_by_event(&path, mask, add_watches);
+        }
+        if mask & DELETE != 0 {
+            evs.push(
+                Event::new(EventKind::Remove(if mask & ISDIR != 0 {
+                    RemoveKind::Folder
+                } else {
+                    RemoveKind::File
+                }))
+                .add_some_path(path.clone()),
+            );
+            self.remove_watch_by_event(&path, remove_watches);
+        }
+        if mask & DELETE_SELF != 0 {
+            let remove_kind = match path.as_ref().and_then(|p| self.watches.get(p)) {
+                Some(Watch { is_dir: true, .. }) => RemoveKind::Folder,
+                Some(Watch { is_dir: false, .. }) => RemoveKind::File,
+                None => RemoveKind::Other,
+            };
+            evs.push(Event::new(EventKind::Remove(remove_kind)).add_some_path(path.clone()));
+            self.remove_watch_by_event(&path, remove_watches);
+        }
+        if mask & MODIFY != 0 {
+            evs.push(
+                Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+                    .add_some_path(path.clone()),
+            );
+        }
+        if mask & CLOSE_WRITE != 0 {
+            evs.push(
+                Event::new(EventKind::Access(AccessKind::Close(AccessMode::Write)))
+                    .add_some_path(path.clone()),
+            );
+        }
+        if mask & ATTRIB != 0 {
+            evs.push(
+                Event::new(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)))
+                    .add_some_path(path),
+            );
+        }
+    }
+
+    fn add_watch_by_event(
+        &self,
+        path: &Option<PathBuf>,
+        mask: u32,
+        add_watches: &mut Vec<PathBuf>,
+    ) {
+        if let Some(ref path) = *path {
+            if mask & ISDIR != 0 {
+                if let Some(parent_path) = path.parent() {
+                    if let Some(watch) = self.watches.get(parent_path) {
+                        if watch.is_recursive {
+                            add_watches.push(path.to_owned());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove_watch_by_event(&self, path: &Option<PathBuf>, remove_watches: &mut Vec<PathBuf>) {
+        if let Some(ref path) = *path {
+            if self.watches.contains_key(path) {
+                remove_watches.push(path.to_owned());
+            }
+        }
+    }
+
+    fn add_watch(
+        &mut self,
+        fs: &MemoryFs,
+        path: PathBuf,
+        is_recursive: bool,
+        mut watch_self: bool,
+    ) -> Result<()> {
+        if !fs.exists(&path) {
+            return Err(Error::io(not_found(&path)).add_path(path));
+        }
+
+        if !is_recursive || !fs.is_dir(&path) {
+            self.add_single_watch(fs, path, false, true);
+            return Ok(());
+        }
+
+        let mut dirs = vec![path.clone()];
+        dirs.extend(
+            fs.state
+                .lock()
+                .unwrap()
+                .nodes
+                .iter()
+                .filter(|(p, node)| node.is_dir() && p.starts_with(&path) && **p != path)
+                .map(|(p, _)| p.clone()),
+        );
+
+        for dir in dirs {
+            self.add_single_watch(fs, dir, is_recursive, watch_self);
+            watch_self = false;
+        }
+
+        Ok(())
+    }
+
+    fn add_single_watch(
+        &mut self,
+        fs: &MemoryFs,
+        path: PathBuf,
+        is_recursive: bool,
+        watch_self: bool,
+    ) {
+        let mut mask = WATCH_MASK;
+        if watch_self {
+            mask |= WATCH_SELF_MASK;
+        }
+        if let Some(old_watch) = self.watches.get(&path) {
+            mask |= old_watch.mask;
+        }
+
+        let is_dir = fs.is_dir(&path);
+        self.watches.insert(
+            path,
+            Watch {
+                mask,
+                is_recursive,
+                is_dir,
+            },
+        );
+    }
+
+    fn remove_watch(&mut self, path: &Path, remove_recursive: bool) -> Result<()> {
+        match self.watches.remove(path) {
+            None => Err(Error::watch_not_found().add_path(path.to_path_buf())),
+            Some(watch) => {
+                if watch.is_recursive || remove_recursive {
+                    self.watches.retain(|p, _| !p.starts_with(path));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `Watcher` implementation for a [`MemoryFs`]
+///
+/// A watcher created with [`Watcher::new`] is attached to a new, empty file system. Use
+/// [`MemoryWatcher::with_fs`] or [`MemoryWatcher::set_fs`] to attach it to an existing one, e.g.
+/// when the watcher is created by a debouncer. [`Config::with_event_coalescing`] applies to the
+/// events of each file system operation.
+pub struct MemoryWatcher {
+    inner: Arc<WatcherInner>,
+    fs: MemoryFs,
+}
+
+impl MemoryWatcher {
+    /// Create a watcher attached to `fs`.
+    pub fn with_fs<F: EventHandler>(fs: &MemoryFs, event_handler: F) -> Self {
+        Self::with_config(fs, event_handler, Config::default())
+    }
+
+    fn with_config<F: EventHandler>(fs: &MemoryFs, event_handler: F, config: Config) -> Self {
+        let inner = Arc::new(WatcherInner {
+            state: Default::default(),
+            event_handler: Mutex::new(Box::new(event_handler)),
+            event_coalescing: config.event_coalescing(),
+        });
+        fs.attach(&inner);
+
+        Self {
+            inner,
+            fs: fs.clone(),
+        }
+    }
+
+    /// Returns the file system this watcher is attached to.
+    pub fn fs(&self) -> MemoryFs {
+        self.fs.clone()
+    }
+
+    /// Attach this watcher to another file system, removing all watches.
+    pub fn set_fs(&mut self, fs: &MemoryFs) {
+        self.fs.detach(&self.inner);
+        *self.inner.state.lock().unwrap() = Default::default();
+        fs.attach(&self.inner);
+        self.fs = fs.clone();
+    }
+}
+
+impl Watcher for MemoryWatcher {
+    fn new<F: EventHandler>(event_handler: F, config: Config) -> Result<Self> {
+        Ok(Self::with_config(&MemoryFs::new(), event_handler, config))
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        self.inner.state.lock()?.add_watch(
+            &self.fs,
+            path.to_path_buf(),
+            recursive_mode.is_recursive(),
+            true,
+        )
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.inner.state.lock()?.remove_watch(path, false)
+    }
+
+    fn kind() -> crate::WatcherKind {
+        crate::WatcherKind::MemoryWatcher
+    }
+}
+
+impl Drop for MemoryWatcher {
+    fn drop(&mut self) {
+        self.fs.detach(&self.inner);
+    }
+}
+
+impl fmt::Debug for MemoryWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryWatcher")
+            .field("watches", &self.inner.state.lock().unwrap().watches)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn setup(mode: RecursiveMode) -> (MemoryFs, MemoryWatcher, mpsc::Receiver<Result<Event>>) {
+        let fs = MemoryFs::new();
+        fs.create_dir_all("/watch").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = MemoryWatcher::with_fs(&fs, tx);
+        watcher.watch(Path::new("/watch"), mode).unwrap();
+        (fs, watcher, rx)
+    }
+
+    fn events(rx: &mpsc::Receiver<Result<Event>>) -> Vec<Event> {
+        rx.try_iter().map(|e| e.unwrap()).collect()
+    }
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(path.into())
+    }
+
+    #[test]
+    fn write_new_and_existing_file() {
+        let (fs, _watcher, rx) = setup(RecursiveMode::Recursive);
+
+        fs.write("/watch/file", b"a").unwrap();
+        fs.write("/watch/file", b"b").unwrap();
+        fs.create_file("/watch/empty").unwrap();
+
+        assert_eq!(
+            events(&rx),
+            vec![
+                event(EventKind::Create(CreateKind::File), "/watch/file"),
+                event(
+                    EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    "/watch/file"
+                ),
+                event(
+                    EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                    "/watch/file"
+                ),
+                event(
+                    EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    "/watch/file"
+                ),
+                event(
+                    EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                    "/watch/file"
+                ),
+                event(EventKind::Create(CreateKind::File), "/watch/empty"),
+                event(
+                    EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                    "/watch/empty"
+                ),
+            ]
+        );
+        assert_eq!(fs.read("/watch/file").unwrap(), b"b");
+    }
+
+    #[test]
+    fn rename_inside_and_out_of_watch() {
+        let (fs, _watcher, rx) = setup(RecursiveMode::Recursive);
+        fs.create_file("/watch/a").unwrap();
+        fs.create_dir("/other").unwrap();
+        let ino = fs.inode("/watch/a");
+        events(&rx);
+
+        fs.rename("/watch/a", "/watch/b").unwrap();
+        fs.rename("/watch/b", "/other/c").unwrap();
+
+        assert_eq!(fs.inode("/other/c"), ino);
+        assert_eq!(
+            events(&rx),
+            vec![
+                event(
+                    EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                    "/watch/a"
+                )
+                .set_tracker(1),
+                event(
+                    EventKind::Modify(ModifyKind::Name(RenameMode::To)),
+                    "/watch/b"
+                )
+                .set_tracker(1),
+                Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                    .set_tracker(1)
+                    .add_path("/watch/a".into())
+                    .add_path("/watch/b".into()),
+                event(
+                    EventKind::Modify(ModifyKind::Name(RenameMode::From)),
+                    "/watch/b"
+                )
+                .set_tracker(2),
+                event(EventKind::Remove(RemoveKind::File), "/watch/b").set_tracker(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn rename_to_itself() {
+        let (fs, _watcher, rx) = setup(RecursiveMode::Recursive);
+        fs.create_file("/watch/file").unwrap();
+        fs.create_dir("/watch/dir").unwrap();
+        let ino = fs.inode("/watch/file");
+        events(&rx);
+
+        fs.rename("/watch/file", "/watch/file").unwrap();
+        fs.rename("/watch/dir", "/watch/dir").unwrap();
+        assert!(fs.rename("/watch/missing", "/watch/missing").is_err());
+
+        assert_eq!(fs.inode("/watch/file"), ino);
+        assert_eq!(events(&rx), vec![]);
+    }
+
+    #[test]
+    fn recursive_watch_follows_new_and_moved_dirs() {
+        let (fs, watcher, rx) = setup(RecursiveMode::Recursive);
+
+        fs.create_dir_all("/watch/a/b").unwrap();
+        fs.create_file("/watch/a/b/file").unwrap();
+        fs.create_dir("/dir").unwrap();
+        fs.rename("/dir", "/watch/dir").unwrap();
+        fs.create_file("/watch/dir/file").unwrap();
+        fs.remove_dir_all("/watch/a").unwrap();
+
+        let paths: Vec<_> = events(&rx)
+            .into_iter()
+            .map(|e| (kind_name(&e.kind), e.paths))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                // `/watch/a/b` is created before `/watch/a` is watched, like with inotify
+                ("create", vec!["/watch/a".into()]),
+                ("create", vec!["/watch/a/b/file".into()]),
+                ("access", vec!["/watch/a/b/file".into()]),
+                ("modify", vec!["/watch/dir".into()]),
+                ("create", vec!["/watch/dir/file".into()]),
+                ("access", vec!["/watch/dir/file".into()]),
+                ("remove", vec!["/watch/a/b/file".into()]),
+                ("remove", vec!["/watch/a/b".into()]),
+                ("remove", vec!["/watch/a".into()]),
+            ]
+        );
+        assert!(!watcher
+            .inner
+            .state
+            .lock()
+            .unwrap()
+            .watches
+            .contains_key(Path::new("/watch/a/b")));
+    }
+
+    #[test]
+    fn non_recursive_watch_and_self_events() {
+        let (fs, _watcher, rx) = setup(RecursiveMode::NonRecursive);
+
+        fs.create_dir("/watch/sub").unwrap();
+        fs.create_file("/watch/sub/ignored").unwrap();
+        fs.remove_dir_all("/watch").unwrap();
+
+        assert_eq!(
+            events(&rx),
+            vec![
+                event(EventKind::Create(CreateKind::Folder), "/watch/sub"),
+                event(EventKind::Remove(RemoveKind::Folder), "/watch/sub"),
+                event(EventKind::Remove(RemoveKind::Folder), "/watch"),
+            ]
+        );
+    }
+
+    #[test]
+    fn watch_file_and_overflow() {
+        let fs = MemoryFs::new();
+        fs.write("/file", b"a").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = MemoryWatcher::with_fs(&fs, tx);
+        watcher
+            .watch(Path::new("/file"), RecursiveMode::Recursive)
+            .unwrap();
+        assert!(watcher
+            .watch(Path::new("/missing"), RecursiveMode::Recursive)
+            .is_err());
+
+        fs.append("/file", b"b").unwrap();
+        fs.overflow();
+        fs.remove_file("/file").unwrap();
+
+        assert_eq!(
+            events(&rx),
+            vec![
+                event(
+                    EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    "/file"
+                ),
+                event(
+                    EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                    "/file"
+                ),
+                Event::new(EventKind::Other).set_flag(Flag::Rescan),
+                event(
+                    EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)),
+                    "/file"
+                ),
+                event(EventKind::Remove(RemoveKind::File), "/file"),
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesce_events() {
+        let fs = MemoryFs::new();
+        fs.create_dir("/watch").unwrap();
+        fs.create_file("/watch/file").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            MemoryWatcher::new(tx, Config::default().with_event_coalescing(true)).unwrap();
+        watcher.set_fs(&fs);
+        // the events for the file are reported to both watches
+        watcher
+            .watch(Path::new("/watch"), RecursiveMode::NonRecursive)
+            .unwrap();
+        watcher
+            .watch(Path::new("/watch/file"), RecursiveMode::NonRecursive)
+            .unwrap();
+
+        fs.write("/watch/file", b"a").unwrap();
+
+        assert_eq!(
+            events(&rx),
+            vec![
+                event(
+                    EventKind::Modify(ModifyKind::Data(DataChange::Any)),
+                    "/watch/file"
+                ),
+                event(
+                    EventKind::Access(AccessKind::Close(AccessMode::Write)),
+                    "/watch/file"
+                ),
+            ]
+        );
+    }
+
+    fn kind_name(kind: &EventKind) -> &'static str {
+        match kind {
+            EventKind::Access(_) => "access",
+            EventKind::Create(_) => "create",
+            EventKind::Modify(ModifyKind::Name(_)) => "modify",
+            EventKind::Remove(_) => "remove",
+            _ => "other",
+        }
+    }
+}
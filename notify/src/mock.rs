@@ -0,0 +1,313 @@
+//! Scriptable `Watcher` implementation for unit tests
+//!
+//! The [`MockWatcher`] never touches the file system. Instead, its [`MockHandle`] is used to
+//! inject events and errors, to inspect which paths were watched and to simulate failures.
+//!
+//! ```
+//! # use std::path::Path;
+//! use notify::{
+//!     event::CreateKind, Config, Event, EventKind, MockWatcher, RecursiveMode, Watcher,
+//! };
+//!
+//! # fn main() -> notify::Result<()> {
+//! let (tx, rx) = std::sync::mpsc::channel();
+//! let mut watcher = MockWatcher::new(tx, Config::default())?;
+//! let handle = watcher.handle();
+//!
+//! // the code under test
+//! watcher.watch(Path::new("/watch"), RecursiveMode::Recursive)?;
+//!
+//! handle.assert_watched("/watch", RecursiveMode::Recursive);
+//!
+//! handle.emit(Event::new(EventKind::Create(CreateKind::File)).add_path("/watch/file".into()));
+//! assert_eq!(rx.recv().unwrap()?.paths, vec![Path::new("/watch/file")]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Config, Error, Event, EventHandler, RecursiveMode, Result, Watcher};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A call made to a [`MockWatcher`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WatchCall {
+    /// [`Watcher::watch`] was called.
+    Watch(PathBuf, RecursiveMode),
+
+    /// [`Watcher::unwatch`] was called.
+    Unwatch(PathBuf),
+
+    /// [`Watcher::configure`] was called.
+    Configure(Config),
+}
+
+#[derive(Debug)]
+struct State {
+    calls: Vec<WatchCall>,
+    watches: HashMap<PathBuf, RecursiveMode>,
+    watch_failures: VecDeque<Error>,
+    unwatch_failures: VecDeque<Error>,
+    configure_result: bool,
+}
+
+/// Mock `Watcher` implementation
+///
+/// Events are only delivered when injected with the [`MockHandle`] returned by
+/// [`MockWatcher::handle`]. See the [module documentation](self) for an example.
+pub struct MockWatcher {
+    state: Arc<Mutex<State>>,
+    event_handler: Arc<Mutex<Box<dyn EventHandler>>>,
+}
+
+impl MockWatcher {
+    /// Returns a handle to control and inspect this watcher.
+    ///
+    /// The handle can be cloned and stays usable after the watcher has been moved, e.g. into a
+    /// debouncer.
+    pub fn handle(&self) -> MockHandle {
+        MockHandle {
+            state: self.state.clone(),
+            event_handler: self.event_handler.clone(),
+        }
+    }
+}
+
+impl Watcher for MockWatcher {
+    fn new<F: EventHandler>(event_handler: F, _config: Config) -> Result<Self> {
+        Ok(Self {
+            state: Arc::new(Mutex::new(State {
+                calls: Vec::new(),
+                watches: HashMap::new(),
+                watch_failures: VecDeque::new(),
+                unwatch_failures: VecDeque::new(),
+                configure_result: false,
+            })),
+            event_handler: Arc::new(Mutex::new(Box::new(event_handler))),
+        })
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        let mut state = self.state.lock()?;
+        state
+            .calls
+            .push(WatchCall::Watch(path.to_path_buf(), recursive_mode));
+
+        if let Some(error) = state.watch_failures.pop_front() {
+            return Err(error);
+        }
+
+        state.watches.insert(path.to_path_buf(), recursive_mode);
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<()> {
+        let mut state = self.state.lock()?;
+        state.calls.push(WatchCall::Unwatch(path.to_path_buf()));
+
+        if let Some(error) = state.unwatch_failures.pop_front() {
+            return Err(error);
+        }
+
+        state
+            .watches
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| Error::watch_not_found().add_path(path.to_path_buf()))
+    }
+
+    fn configure(&mut self, option: Config) -> Result<bool> {
+        let mut state = self.state.lock()?;
+        state.calls.push(WatchCall::Configure(option));
+        Ok(state.configure_result)
+    }
+
+    fn kind() -> crate::WatcherKind {
+        crate::WatcherKind::MockWatcher
+    }
+}
+
+impl fmt::Debug for MockWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockWatcher")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+/// Handle to control and inspect a [`MockWatcher`]
+#[derive(Clone)]
+pub struct MockHandle {
+    state: Arc<Mutex<State>>,
+    event_handler: Arc<Mutex<Box<dyn EventHandler>>>,
+}
+
+impl MockHandle {
+    /// Deliver an event to the event handler of the watcher.
+    ///
+    /// The event handler is called on the current thread.
+    pub fn emit(&self, event: Event) {
+        self.send(Ok(event));
+    }
+
+    /// Deliver an error to the event handler of the watcher.
+    pub fn emit_error(&self, error: Error) {
+        self.send(Err(error));
+    }
+
+    /// Deliver an event or error to the event handler of the watcher.
+    pub fn send(&self, event: Result<Event>) {
+        self.event_handler.lock().unwrap().handle_event(event);
+    }
+
+    /// Returns all calls made to the watcher so far, in order.
+    pub fn calls(&self) -> Vec<WatchCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Returns the recursive mode of `path` if it is currently watched.
+    pub fn watched(&self, path: impl AsRef<Path>) -> Option<RecursiveMode> {
+        self.state
+            .lock()
+            .unwrap()
+            .watches
+            .get(path.as_ref())
+            .copied()
+    }
+
+    /// Returns all currently watched paths.
+    pub fn watched_paths(&self) -> HashMap<PathBuf, RecursiveMode> {
+        self.state.lock().unwrap().watches.clone()
+    }
+
+    /// Panics if `path` is not currently watched with `recursive_mode`.
+    #[track_caller]
+    pub fn assert_watched(&self, path: impl AsRef<Path>, recursive_mode: RecursiveMode) {
+        let path = path.as_ref();
+        assert_eq!(
+            self.watched(path),
+            Some(recursive_mode),
+            "path {:?} not watched as expected",
+            path
+        );
+    }
+
+    /// Panics if `path` is currently watched.
+    #[track_caller]
+    pub fn assert_not_watched(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        assert_eq!(self.watched(path), None, "path {:?} is watched", path);
+    }
+
+    /// Make the next call to [`Watcher::watch`] fail with `error`.
+    ///
+    /// Can be called multiple times to fail multiple calls, the path is not watched on failure.
+    pub fn fail_next_watch(&self, error: Error) {
+        self.state.lock().unwrap().watch_failures.push_back(error);
+    }
+
+    /// Make the next call to [`Watcher::unwatch`] fail with `error`.
+    ///
+    /// Can be called multiple times to fail multiple calls, the path stays watched on failure.
+    pub fn fail_next_unwatch(&self, error: Error) {
+        self.state.lock().unwrap().unwatch_failures.push_back(error);
+    }
+
+    /// Set the value returned by [`Watcher::configure`], `false` by default.
+    pub fn set_configure_result(&self, supported: bool) {
+        self.state.lock().unwrap().configure_result = supported;
+    }
+}
+
+impl fmt::Debug for MockHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockHandle")
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{event::CreateKind, ErrorKind, EventKind};
+    use std::sync::mpsc;
+
+    #[test]
+    fn records_calls() {
+        let mut watcher = MockWatcher::new(|_: Result<Event>| {}, Config::default()).unwrap();
+        let handle = watcher.handle();
+
+        watcher
+            .watch(Path::new("/a"), RecursiveMode::Recursive)
+            .unwrap();
+        watcher
+            .watch(Path::new("/b"), RecursiveMode::NonRecursive)
+            .unwrap();
+        watcher.unwatch(Path::new("/a")).unwrap();
+        assert!(matches!(
+            watcher.unwatch(Path::new("/c")).unwrap_err().kind,
+            ErrorKind::WatchNotFound
+        ));
+
+        handle.assert_not_watched("/a");
+        handle.assert_watched("/b", RecursiveMode::NonRecursive);
+        assert_eq!(
+            handle.calls(),
+            vec![
+                WatchCall::Watch("/a".into(), RecursiveMode::Recursive),
+                WatchCall::Watch("/b".into(), RecursiveMode::NonRecursive),
+                WatchCall::Unwatch("/a".into()),
+                WatchCall::Unwatch("/c".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn simulates_failures() {
+        let mut watcher = MockWatcher::new(|_: Result<Event>| {}, Config::default()).unwrap();
+        let handle = watcher.handle();
+
+        handle.fail_next_watch(Error::new(ErrorKind::MaxFilesWatch));
+        assert!(matches!(
+            watcher
+                .watch(Path::new("/a"), RecursiveMode::Recursive)
+                .unwrap_err()
+                .kind,
+            ErrorKind::MaxFilesWatch
+        ));
+        handle.assert_not_watched("/a");
+
+        watcher
+            .watch(Path::new("/a"), RecursiveMode::Recursive)
+            .unwrap();
+        handle.fail_next_unwatch(Error::generic("busy"));
+        assert!(watcher.unwatch(Path::new("/a")).is_err());
+        handle.assert_watched("/a", RecursiveMode::Recursive);
+
+        assert!(!watcher.configure(Config::default()).unwrap());
+        handle.set_configure_result(true);
+        assert!(watcher.configure(Config::default()).unwrap());
+    }
+
+    #[test]
+    fn injects_events() {
+        let (tx, rx) = mpsc::channel();
+        let watcher = MockWatcher::new(tx, Config::default()).unwrap();
+        let handle = watcher.handle();
+
+        let event = Event::new(EventKind::Create(CreateKind::File)).add_path("/a".into());
+        handle.emit(event.clone());
+        handle.emit_error(Error::path_not_found());
+
+        assert_eq!(rx.recv().unwrap().unwrap(), event);
+        assert!(matches!(
+            rx.recv().unwrap().unwrap_err().kind,
+            ErrorKind::PathNotFound
+        ));
+    }
+}
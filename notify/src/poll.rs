@@ -56,6 +56,7 @@ impl ScanEventHandler for () {
 use data::{DataBuilder, WatchData};
 mod data {
     use crate::{
+        coalesce::EventBatch,
         event::{CreateKind, DataChange, Event, EventKind, MetadataKind, ModifyKind, RemoveKind},
         EventHandler,
     };
@@ -91,6 +92,7 @@ mod data {
         pub(super) fn new<F, G>(
             event_handler: F,
             compare_content: bool,
+            event_coalescing: bool,
             scan_emitter: Option<G>,
         ) -> Self
         where
@@ -107,7 +109,7 @@ mod data {
                 }
             };
             Self {
-                emitter: EventEmitter::new(event_handler),
+                emitter: EventEmitter::new(event_handler, event_coalescing),
                 scan_emitter,
                 build_hasher: compare_content.then(RandomState::default),
                 now: Instant::now(),
@@ -119,6 +121,11 @@ mod data {
             self.now = Instant::now();
         }
 
+        /// Pass all events held back for coalescing to the event handler.
+        pub(super) fn flush_events(&self) {
+            self.emitter.flush();
+        }
+
         /// Create [`WatchData`].
         ///
         /// This function will return `Err(_)` if can not retrieve metadata from
@@ -437,16 +444,26 @@ mod data {
         // Use `RefCell` to make sure `emit()` only need shared borrow of self (&self).
         // Use `Box` to make sure EventEmitter is Sized.
         Box<RefCell<dyn EventHandler>>,
+        // Events of the current poll pass, if coalescing is enabled.
+        RefCell<EventBatch>,
     );
 
     impl EventEmitter {
-        fn new<F: EventHandler>(event_handler: F) -> Self {
-            Self(Box::new(RefCell::new(event_handler)))
+        fn new<F: EventHandler>(event_handler: F, event_coalescing: bool) -> Self {
+            Self(
+                Box::new(RefCell::new(event_handler)),
+                RefCell::new(EventBatch::new(event_coalescing)),
+            )
         }
 
         /// Emit single event.
         fn emit(&self, event: crate::Result<Event>) {
-            self.0.borrow_mut().handle_event(event);
+            self.1.borrow_mut().push(event, &mut *self.0.borrow_mut());
+        }
+
+        /// Emit all events held back for coalescing.
+        fn flush(&self) {
+            self.1.borrow_mut().flush(&mut *self.0.borrow_mut());
         }
 
         /// Emit event.
@@ -513,8 +530,12 @@ impl PollWatcher {
         config: Config,
         scan_callback: Option<G>,
     ) -> crate::Result<PollWatcher> {
-        let data_builder =
-            DataBuilder::new(event_handler, config.compare_contents(), scan_callback);
+        let data_builder = DataBuilder::new(
+            event_handler,
+            config.compare_contents(),
+            config.event_coalescing(),
+            scan_callback,
+        );
 
         let (tx, rx) = unbounded();
 
@@ -558,6 +579,7 @@ impl PollWatcher {
                         for watch_data in vals {
                             watch_data.rescan(&mut data_builder);
                         }
+                        data_builder.flush_events();
                     }
                     // TODO: v7.0 use delay - (Instant::now().saturating_duration_since(start))
                     if let Some(delay) = delay {
@@ -589,6 +611,7 @@ impl PollWatcher {
             if let Some(watch_data) = watch_data {
                 watches.insert(path.to_path_buf(), watch_data);
             }
+            data_builder.flush_events();
         }
     }
 
@@ -0,0 +1,141 @@
+//! Pairing of rename events by tracker
+//!
+//! Used by backends that report both halves of a rename with a shared cookie, like inotify.
+
+use crate::event::{Event, EventKind, RemoveKind};
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+struct PendingRename {
+    event: Event,
+    is_dir: bool,
+    expires: Instant,
+}
+
+/// Rename `From` events waiting for the matching `To` event
+///
+/// Any number of renames can be in flight at the same time. A `From` event that isn't matched
+/// within the timeout was a move out of the watched tree and is turned into a `Remove` event.
+#[derive(Debug)]
+pub(crate) struct RenameTable {
+    pending: Vec<PendingRename>,
+    timeout: Duration,
+}
+
+impl RenameTable {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: Vec::new(),
+            timeout,
+        }
+    }
+
+    /// Add a `From` event, it has to have a tracker.
+    pub fn add_from(&mut self, event: Event, is_dir: bool, now: Instant) {
+        self.pending.push(PendingRename {
+            event,
+            is_dir,
+            expires: now + self.timeout,
+        });
+    }
+
+    /// Take the `From` event matching `tracker`.
+    ///
+    /// The expiry isn't checked here, a `From` event can be matched until [`expire`](Self::expire)
+    /// removed it.
+    pub fn take_from(&mut self, tracker: usize) -> Option<Event> {
+        let index = self
+            .pending
+            .iter()
+            .position(|pending| pending.event.tracker() == Some(tracker))?;
+        Some(self.pending.remove(index).event)
+    }
+
+    /// Remove all `From` events that expired at `now` and return the `Remove` events replacing
+    /// them, in the order the renames occurred.
+    pub fn expire(&mut self, now: Instant) -> Vec<Event> {
+        let mut removed = Vec::new();
+        self.pending.retain(|pending| {
+            if pending.expires <= now {
+                removed.push(remove_event(pending));
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Remove all `From` events, regardless of their expiry.
+    #[cfg(any(test, feature = "memory"))]
+    pub fn expire_all(&mut self) -> Vec<Event> {
+        self.pending.drain(..).map(|p| remove_event(&p)).collect()
+    }
+
+    /// Returns when the next `From` event expires.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.pending.iter().map(|pending| pending.expires).min()
+    }
+}
+
+fn remove_event(pending: &PendingRename) -> Event {
+    let kind = if pending.is_dir {
+        RemoveKind::Folder
+    } else {
+        RemoveKind::File
+    };
+    let mut event = Event::new(EventKind::Remove(kind));
+    event.paths = pending.event.paths.clone();
+    event.attrs = pending.event.attrs.clone();
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{ModifyKind, RenameMode};
+
+    fn from(path: &str, tracker: usize) -> Event {
+        Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::From)))
+            .add_path(path.into())
+            .set_tracker(tracker)
+    }
+
+    #[test]
+    fn pairs_concurrent_renames() {
+        let now = Instant::now();
+        let mut table = RenameTable::new(Duration::from_millis(100));
+        table.add_from(from("/a", 1), false, now);
+        table.add_from(from("/b", 2), false, now);
+
+        assert_eq!(table.take_from(2), Some(from("/b", 2)));
+        assert_eq!(table.take_from(2), None);
+        assert_eq!(table.take_from(1), Some(from("/a", 1)));
+        assert_eq!(table.next_expiry(), None);
+    }
+
+    #[test]
+    fn expires_unmatched_renames() {
+        let now = Instant::now();
+        let timeout = Duration::from_millis(100);
+        let mut table = RenameTable::new(timeout);
+        table.add_from(from("/a", 1), true, now);
+        table.add_from(from("/b", 2), false, now + timeout / 2);
+
+        assert_eq!(table.next_expiry(), Some(now + timeout));
+        assert!(table.expire(now).is_empty());
+        assert_eq!(
+            table.expire(now + timeout),
+            vec![Event::new(EventKind::Remove(RemoveKind::Folder))
+                .add_path("/a".into())
+                .set_tracker(1)]
+        );
+        assert_eq!(table.take_from(1), None);
+        assert_eq!(
+            table.expire_all(),
+            vec![Event::new(EventKind::Remove(RemoveKind::File))
+                .add_path("/b".into())
+                .set_tracker(2)]
+        );
+    }
+}
@@ -0,0 +1,491 @@
+//! Recording and replaying of event streams
+//!
+//! Watcher bugs often depend on the timing of events, which makes them hard to reproduce. The
+//! [`EventRecorder`] is an [`EventHandler`] that writes all events it receives, together with the
+//! time they arrived, to a file. The [`ReplayWatcher`] reads such a [`Recording`] and emits the
+//! events again, with the original or a scaled timing.
+//!
+//! Each line of a recording consists of the number of milliseconds since the recording was started
+//! and an event encoded with one of the [`format`](crate::event::format)s. Errors are recorded as
+//! `error` followed by the quoted error message:
+//!
+//! ```text
+//! 0 create(file) /watch/file
+//! 12 modify(data) /watch/file
+//! 15 error "No path was found."
+//! ```
+//!
+//! ```no_run
+//! # use std::path::Path;
+//! use notify::{
+//!     replay::{EventRecorder, Recording, Timing},
+//!     Config, RecommendedWatcher, RecursiveMode, ReplayWatcher, Watcher,
+//! };
+//!
+//! # fn main() -> notify::Result<()> {
+//! // record the events of the recommended watcher
+//! let recorder = EventRecorder::create("events.log")?;
+//! let mut watcher = RecommendedWatcher::new(recorder, Config::default())?;
+//! watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+//! # drop(watcher);
+//!
+//! // later, replay them
+//! let recording = Recording::open("events.log")?;
+//! let handler = |event: notify::Result<notify::Event>| println!("{:?}", event);
+//! let mut watcher = ReplayWatcher::new(handler, Config::default())?;
+//! watcher.watch(Path::new("."), RecursiveMode::Recursive)?;
+//! watcher.replay(&recording, Timing::Original)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::event::format::{self, Format};
+use crate::{Config, Error, Event, EventHandler, RecursiveMode, Result, Watcher};
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Event handler that writes all events to a recording
+///
+/// Events can be forwarded to another handler, so a recording can be created while the
+/// application keeps working as usual. See the [module documentation](self) for the file format.
+pub struct EventRecorder<W: Write> {
+    writer: W,
+    format: Format,
+    start: Instant,
+    forward: Option<Box<dyn EventHandler>>,
+}
+
+impl EventRecorder<BufWriter<File>> {
+    /// Create a recorder writing to a new file at `path`, truncating an existing file.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let file =
+            File::create(path.as_ref()).map_err(|e| Error::io(e).add_path(path.as_ref().into()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> EventRecorder<W> {
+    /// Create a recorder writing to `writer`, using the text format.
+    ///
+    /// The time of recorded events is relative to the creation of the recorder.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            format: Format::Text,
+            start: Instant::now(),
+            forward: None,
+        }
+    }
+
+    /// Set the format used to encode events.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Forward all events to `event_handler` after recording them.
+    pub fn with_handler<F: EventHandler>(mut self, event_handler: F) -> Self {
+        self.forward = Some(Box::new(event_handler));
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn record(&mut self, event: &Result<Event>) -> Result<()> {
+        let millis = self.start.elapsed().as_millis();
+        let line = match event {
+            Ok(event) => format!("{} {}", millis, self.format.encode(event)?),
+            Err(error) => format!("{} error {}", millis, format::quote_str(&error.to_string())),
+        };
+
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Send + 'static> EventHandler for EventRecorder<W> {
+    fn handle_event(&mut self, event: Result<Event>) {
+        if let Err(e) = self.record(&event) {
+            log::warn!("failed to record event: {e}");
+        }
+
+        if let Some(ref mut forward) = self.forward {
+            forward.handle_event(event);
+        }
+    }
+}
+
+impl<W: Write> fmt::Debug for EventRecorder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventRecorder")
+            .field("format", &self.format)
+            .field("start", &self.start)
+            .finish()
+    }
+}
+
+/// A single entry of a [`Recording`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedEvent {
+    /// Time of the event, relative to the start of the recording.
+    pub time: Duration,
+
+    /// The recorded event, or the message of a recorded error.
+    pub event: std::result::Result<Event, String>,
+}
+
+/// A recorded event stream, see the [module documentation](self) for the file format
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    /// The recorded events, in chronological order.
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    /// Read a recording from the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file =
+            File::open(path.as_ref()).map_err(|e| Error::io(e).add_path(path.as_ref().into()))?;
+        Self::read(BufReader::new(file))
+    }
+
+    /// Read a recording from `reader`.
+    ///
+    /// Empty lines and lines starting with `#` are skipped. Both the text and the JSON Lines
+    /// format are accepted, the latter only with the `serde` feature.
+    pub fn read(reader: impl BufRead) -> Result<Self> {
+        let mut events = Vec::new();
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = Self::parse_line(line)
+                .map_err(|e| Error::generic(&format!("line {}: {}", index + 1, e)))?;
+            events.push(entry);
+        }
+
+        Ok(Self { events })
+    }
+
+    fn parse_line(line: &str) -> Result<RecordedEvent> {
+        let (millis, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let millis = millis
+            .parse()
+            .map_err(|_| Error::generic(&format!("invalid time `{}`", millis)))?;
+        let rest = rest.trim_start();
+
+        let event = if let Some(message) = rest.strip_prefix("error ") {
+            Err(format::unquote_str(message)?)
+        } else if rest.starts_with('{') {
+            #[cfg(feature = "serde")]
+            {
+                Ok(Format::Json.decode(rest)?)
+            }
+            #[cfg(not(feature = "serde"))]
+            {
+                return Err(Error::generic(
+                    "JSON recordings require the `serde` feature",
+                ));
+            }
+        } else {
+            Ok(Format::Text.decode(rest)?)
+        };
+
+        Ok(RecordedEvent {
+            time: Duration::from_millis(millis),
+            event,
+        })
+    }
+
+    /// Write the recording to `writer`, using the given event format.
+    pub fn write(&self, mut writer: impl Write, format: Format) -> Result<()> {
+        for entry in &self.events {
+            let millis = entry.time.as_millis();
+            match &entry.event {
+                Ok(event) => writeln!(writer, "{} {}", millis, format.encode(event)?)?,
+                Err(message) => {
+                    writeln!(writer, "{} error {}", millis, format::quote_str(message))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The timing used to replay a [`Recording`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timing {
+    /// Keep the delays between events as recorded.
+    Original,
+
+    /// Divide the recorded delays by the given factor, e.g. `2.0` replays twice as fast.
+    Scaled(f64),
+
+    /// Emit all events without any delay.
+    Immediate,
+}
+
+impl Timing {
+    /// The delay of an event recorded at `time` since the start of the replay
+    ///
+    /// Fails if the scaled delay doesn't fit into a `Duration`.
+    fn delay(self, time: Duration) -> Result<Option<Duration>> {
+        match self {
+            Timing::Original => Ok(Some(time)),
+            Timing::Scaled(factor) => {
+                // like `Duration::try_from_secs_f64`, which needs Rust 1.66
+                let secs = time.as_secs_f64() / factor;
+                if secs.is_finite() && (0.0..u64::MAX as f64).contains(&secs) {
+                    Ok(Some(Duration::from_secs_f64(secs)))
+                } else {
+                    Err(Error::generic(&format!(
+                        "Replay delay of {:?} is too long with speed factor {}",
+                        time, factor
+                    )))
+                }
+            }
+            Timing::Immediate => Ok(None),
+        }
+    }
+}
+
+/// `Watcher` implementation replaying a [`Recording`]
+///
+/// No events are delivered until [`ReplayWatcher::replay`] is called. Only events with a path
+/// inside one of the watched paths are emitted, events without a path, like rescan notices, are
+/// always emitted. See the [module documentation](self) for an example.
+pub struct ReplayWatcher {
+    event_handler: Box<dyn EventHandler>,
+    watches: HashMap<PathBuf, RecursiveMode>,
+}
+
+impl ReplayWatcher {
+    /// Emit the events of `recording`, blocking until all events are delivered.
+    pub fn replay(&mut self, recording: &Recording, timing: Timing) -> Result<()> {
+        if let Timing::Scaled(factor) = timing {
+            if !factor.is_normal() || factor < 0.0 {
+                return Err(Error::generic(&format!(
+                    "Invalid replay speed factor {}",
+                    factor
+                )));
+            }
+        }
+
+        // fail before emitting any event
+        let delays = recording
+            .events
+            .iter()
+            .map(|entry| timing.delay(entry.time))
+            .collect::<Result<Vec<_>>>()?;
+
+        let start = Instant::now();
+
+        for (entry, delay) in recording.events.iter().zip(delays) {
+            if let Some(delay) = delay {
+                let now = start.elapsed();
+                if delay > now {
+                    thread::sleep(delay - now);
+                }
+            }
+
+            match &entry.event {
+                Ok(event) => {
+                    if self.is_watched(event) {
+                        self.event_handler.handle_event(Ok(event.clone()));
+                    }
+                }
+                Err(message) => self
+                    .event_handler
+                    .handle_event(Err(Error::generic(message))),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the recording at `path` and emit its events, see [`ReplayWatcher::replay`].
+    pub fn replay_file(&mut self, path: impl AsRef<Path>, timing: Timing) -> Result<()> {
+        let recording = Recording::open(path)?;
+        self.replay(&recording, timing)
+    }
+
+    fn is_watched(&self, event: &Event) -> bool {
+        event.paths.is_empty()
+            || event.paths.iter().any(|path| {
+                self.watches.iter().any(|(root, recursive_mode)| {
+                    path == root
+                        || match recursive_mode {
+                            RecursiveMode::Recursive => path.starts_with(root),
+                            RecursiveMode::NonRecursive => path.parent() == Some(root.as_path()),
+                        }
+                })
+            })
+    }
+}
+
+impl Watcher for ReplayWatcher {
+    fn new<F: EventHandler>(event_handler: F, _config: Config) -> Result<Self> {
+        Ok(Self {
+            event_handler: Box::new(event_handler),
+            watches: HashMap::new(),
+        })
+    }
+
+    fn watch(&mut self, path: &Path, recursive_mode: RecursiveMode) -> Result<()> {
+        self.watches.insert(path.to_path_buf(), recursive_mode);
+        Ok(())
+    }
+
+    fn unwatch(&mut self, path: &Path) -> Result<()> {
+        self.watches
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| Error::watch_not_found().add_path(path.into()))
+    }
+
+    fn kind() -> crate::WatcherKind {
+        crate::WatcherKind::ReplayWatcher
+    }
+}
+
+impl fmt::Debug for ReplayWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplayWatcher")
+            .field("watches", &self.watches)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{CreateKind, DataChange, EventKind, Flag, ModifyKind};
+    use std::sync::mpsc;
+
+    fn recorded() -> Vec<u8> {
+        let mut recorder = EventRecorder::new(Vec::new());
+        recorder.handle_event(Ok(
+            Event::new(EventKind::Create(CreateKind::File)).add_path("/watch/file".into())
+        ));
+        recorder.handle_event(Ok(Event::new(EventKind::Modify(ModifyKind::Data(
+            DataChange::Any,
+        )))
+        .add_path("/other/file".into())));
+        recorder.handle_event(Err(Error::generic("something \"failed\"")));
+        recorder.handle_event(Ok(Event::new(EventKind::Other).set_flag(Flag::Rescan)));
+        recorder.into_inner()
+    }
+
+    #[test]
+    fn record_and_read() {
+        let recording = Recording::read(&recorded()[..]).unwrap();
+
+        assert_eq!(recording.events.len(), 4);
+        assert_eq!(
+            recording.events[2].event,
+            Err("something \"failed\"".to_string())
+        );
+
+        let mut written = Vec::new();
+        recording.write(&mut written, Format::Text).unwrap();
+        assert_eq!(Recording::read(&written[..]).unwrap(), recording);
+    }
+
+    #[test]
+    fn read_text_recording() {
+        let recording = Recording::read(
+            &b"# comment\n\n0 create(file) /watch/file\n15 modify(data) \"/watch/a b\"\n"[..],
+        )
+        .unwrap();
+
+        assert_eq!(
+            recording.events,
+            vec![
+                RecordedEvent {
+                    time: Duration::from_millis(0),
+                    event: Ok(Event::new(EventKind::Create(CreateKind::File))
+                        .add_path("/watch/file".into())),
+                },
+                RecordedEvent {
+                    time: Duration::from_millis(15),
+                    event: Ok(
+                        Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+                            .add_path("/watch/a b".into())
+                    ),
+                },
+            ]
+        );
+        assert!(Recording::read(&b"abc create /path"[..]).is_err());
+    }
+
+    #[test]
+    fn replay_watched_paths() {
+        let recording = Recording::read(&recorded()[..]).unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        let mut watcher = ReplayWatcher::new(tx, Config::default()).unwrap();
+        watcher
+            .watch(Path::new("/watch"), RecursiveMode::Recursive)
+            .unwrap();
+        watcher.replay(&recording, Timing::Immediate).unwrap();
+        drop(watcher);
+
+        let received: Vec<_> = rx.into_iter().collect();
+        assert_eq!(received.len(), 3);
+        assert_eq!(
+            received[0].as_ref().unwrap().paths,
+            vec![PathBuf::from("/watch/file")]
+        );
+        assert!(received[1].is_err());
+        assert!(received[2].as_ref().unwrap().need_rescan());
+    }
+
+    #[test]
+    fn replay_scaled() {
+        let recording = Recording::read(&b"0 create /watch/a\n200 create /watch/b\n"[..]).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = ReplayWatcher::new(tx, Config::default()).unwrap();
+        watcher
+            .watch(Path::new("/watch"), RecursiveMode::NonRecursive)
+            .unwrap();
+
+        assert_eq!(
+            Timing::Scaled(4.0)
+                .delay(Duration::from_millis(200))
+                .unwrap(),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(
+            Timing::Immediate.delay(Duration::from_millis(200)).unwrap(),
+            None
+        );
+
+        // the delays shrink to nanoseconds, so the test doesn't wait
+        watcher.replay(&recording, Timing::Scaled(1e6)).unwrap();
+        assert_eq!(rx.try_iter().count(), 2);
+
+        assert!(watcher.replay(&recording, Timing::Scaled(0.0)).is_err());
+
+        // the delay overflows, nothing is emitted
+        assert!(Timing::Scaled(1e-300)
+            .delay(Duration::from_millis(200))
+            .is_err());
+        assert!(watcher.replay(&recording, Timing::Scaled(1e-300)).is_err());
+        assert_eq!(rx.try_iter().count(), 0);
+    }
+}